@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 use clap::{Parser, Subcommand};
+use crate::db::transfer::Format;
 
 #[derive(Parser, Clone)]
 pub struct CLI {
@@ -31,6 +32,42 @@ pub enum DatabaseCommand {
         #[command(subcommand)]
         command: DatabaseMigrationCommand
     },
+    Pull {
+        #[arg(short, long)]
+        force: bool,
+    },
+    Codegen {
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    Docs,
+    Explain {
+        /// `user.Login` plans with placeholder arguments, `user.Login("ann", "pw")` with these
+        expression: String,
+        /// also runs the query, in a transaction that is rolled back
+        #[arg(long)]
+        analyze: bool,
+    },
+    Seed {
+        sets: Vec<String>,
+    },
+    /// Encrypted fields are written in plaintext, hashed fields as their hashes
+    Export {
+        model: String,
+        #[arg(short, long, default_value = "csv")]
+        format: Format,
+        /// written to standard output when omitted
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    Import {
+        model: String,
+        file: PathBuf,
+        /// read from the file's extension when omitted
+        #[arg(short, long)]
+        format: Option<Format>,
+    },
+    Stats,
 }
 
 #[derive(Subcommand, Clone)]
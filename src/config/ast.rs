@@ -361,6 +361,11 @@ impl<'a> ConfigParser<'a> {
 }
 
 impl Section {
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &Ident> {
+        self.inner.keys()
+    }
+
     #[inline]
     pub fn get_raw(&self, key: impl AsRef<str>) -> Option<&Value> {
         self.inner.get(key.as_ref())
@@ -382,6 +387,19 @@ impl Section {
         }
     }
 
+    /// `Ok(None)` when `key` is missing, otherwise the result of `get`
+    pub fn get_optional<T>(
+        &self,
+        key: impl AsRef<str>,
+        get: impl FnOnce(&Self, &str) -> Result<T, EvaluationError>,
+    ) -> Result<Option<T>, EvaluationError> {
+        let key = key.as_ref();
+        match self.get_raw(key) {
+            Some(_) => get(self, key).map(Some),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_section(&self, key: impl AsRef<str>) -> Result<&Section, EvaluationError> {
         let key = key.as_ref();
         match self.get_raw(key) {
@@ -1,5 +1,11 @@
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use crate::db::parser::qql::Statement;
+use crate::db::quote::Quoted;
+use crate::db::rules::Rule;
 use crate::db::types::Type;
+use crate::db::value::Value;
 use crate::parser::Ident;
 
 #[derive(Debug, Clone)]
@@ -11,16 +17,112 @@ pub struct Model {
 impl Model {
     #[inline]
     pub fn has_field(&self, field: impl AsRef<str>) -> bool {
+        self.get_field(field).is_some()
+    }
+
+    #[inline]
+    pub fn get_field(&self, field: impl AsRef<str>) -> Option<&ModelField> {
         let field = field.as_ref();
         self.fields.iter()
-            .any(|f| f.name == field)
+            .find(|f| f.name == field)
+    }
+
+    /// The field marked `@primary`.
+    pub fn primary_key(&self) -> Option<&ModelField> {
+        self.fields.iter()
+            .find(|f| f.index == Some(Index::Primary))
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ModelField {
     pub name: Ident,
-    pub repr: Rc<dyn Type>,
+    /// the name the type was written as, `Hashed` or an enum
+    pub type_name: Ident,
+    pub repr: Arc<dyn Type>,
     pub optional: bool,
-    pub arg: Option<u64>,
-}
\ No newline at end of file
+    pub args: Vec<u64>,
+    /// already converted to the field's type
+    pub default: Option<Value>,
+    pub timestamp: Option<Timestamp>,
+    pub index: Option<Index>,
+    pub rules: Vec<Rule>,
+}
+
+impl ModelField {
+    /// The value an insert stores when it does not set the field.
+    pub fn default_value(&self) -> Option<Value> {
+        match self.timestamp {
+            Some(_) => Some(Value::DateTime(Utc::now().naive_utc())),
+            None => self.default.clone(),
+        }
+    }
+}
+
+/// An index migrations create on a single field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Index {
+    /// `@index`
+    Plain,
+    /// `@unique`, no two rows store the same value
+    Unique,
+    /// `@primary`, declared with the table
+    Primary,
+}
+
+impl Index {
+    /// `create index if not exists "User_email_1f2e3d4c_idx" on "User" (email)`, primary keys are
+    /// part of the create table statement instead.
+    pub fn create_statement(&self, model: &Model, field: &ModelField) -> Option<String> {
+        let (unique, suffix) = match self {
+            Index::Plain => ("", "idx"),
+            Index::Unique => ("unique ", "key"),
+            Index::Primary => return None,
+        };
+        Some(format!(
+            "create {unique}index if not exists {} on {} ({})",
+            Quoted(&Self::name(model, field, suffix)), Quoted(&model.name), Quoted(&field.name),
+        ))
+    }
+
+    /// `{model}_{field}_{hash}_{suffix}`. The hash of the pair keeps `A_b` + `c` apart from `A` +
+    /// `b_c`, and keeps names postgres truncates to 63 bytes apart by what was cut off.
+    pub(crate) fn name(model: &Model, field: &ModelField, suffix: &str) -> String {
+        const MAX_LEN: usize = 63;
+
+        // FNV-1a, index names must not change between builds
+        let mut hash: u32 = 0x811c9dc5;
+        for byte in model.name.as_str().bytes().chain([0]).chain(field.name.as_str().bytes()) {
+            hash = (hash ^ byte as u32).wrapping_mul(0x01000193);
+        }
+        let tail = format!("_{hash:08x}_{suffix}");
+
+        let mut name = format!("{}_{}", model.name, field.name);
+        if name.len() + tail.len() > MAX_LEN {
+            let mut end = MAX_LEN - tail.len();
+            while !name.is_char_boundary(end) {
+                end -= 1;
+            }
+            name.truncate(end);
+        }
+        name + &tail
+    }
+}
+
+/// Fields maintained by the compiled queries, both are set on insert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    /// `@created_at`
+    CreatedAt,
+    /// `@updated_at`, also set by every update
+    UpdatedAt,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub name: Ident,
+    pub args: Vec<Ident>,
+    pub statement: Statement,
+    /// `@cache(60s)`, how long the results of a select are reused for the same arguments
+    pub cache: Option<Duration>,
+}
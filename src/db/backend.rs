@@ -1,6 +1,9 @@
-
 mod driver;
+mod memory;
 mod postgres;
+mod sqlite;
 
-pub use driver::Driver;
-pub use postgres::PostgresDriver;
+pub use driver::{ColumnSchema, Driver, PlanNode, QueryPlan, TableSchema, Transaction};
+pub use memory::MemoryDriver;
+pub use postgres::{ConnectOptions, PoolOptions, PostgresDialect, PostgresDriver, SslMode};
+pub use sqlite::SqliteDriver;
@@ -1,6 +1,104 @@
-use crate::db::ast::Model;
+use anyhow::bail;
+use async_trait::async_trait;
+use hashbrown::HashMap;
+use crate::db::ast::{Model, Query};
+use crate::db::types::DataType;
+use crate::db::value::{QueryResult, Value};
+use crate::parser::Ident;
 
-pub trait Driver {
-    fn migrate_up(&mut self, model: &Model) -> anyhow::Result<()>;
-    fn migrate_down(&mut self, model: &Model) -> anyhow::Result<()>;
-}
\ No newline at end of file
+/// Drivers are shared between concurrently running handlers, any connection state lives behind a
+/// pool or a lock inside the driver.
+#[async_trait]
+pub trait Driver: Send + Sync {
+    async fn migrate_up(&self, model: &Model) -> anyhow::Result<()>;
+    async fn migrate_down(&self, model: &Model) -> anyhow::Result<()>;
+    async fn introspect(&self) -> anyhow::Result<Vec<TableSchema>>;
+    async fn execute(
+        &self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult>;
+
+    /// Runs a select and hands its rows to `row` as they arrive instead of collecting them, so
+    /// tables larger than memory can be read. Drivers that hold every row anyway use `execute`.
+    async fn stream(
+        &self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+        row: &mut (dyn FnMut(Vec<Value>) -> anyhow::Result<()> + Send),
+    ) -> anyhow::Result<()> {
+        for values in self.execute(models, query, args).await?.rows {
+            row(values)?;
+        }
+        Ok(())
+    }
+
+    /// The plan the database picks for a query. `analyze` also runs it to report what each step
+    /// took, in a transaction that is rolled back so writes leave no trace.
+    async fn explain(
+        &self,
+        _models: &HashMap<Ident, Model>,
+        _query: &Query,
+        _args: &[Value],
+        _analyze: bool,
+    ) -> anyhow::Result<QueryPlan> {
+        bail!("the database has no query plans")
+    }
+
+    /// Checks a connection out of the driver and opens a transaction on it.
+    async fn begin(&self) -> anyhow::Result<Box<dyn Transaction>>;
+}
+
+/// Queries executed through a transaction share its connection. Dropping a transaction without
+/// calling `commit` rolls it back.
+#[async_trait]
+pub trait Transaction: Send {
+    async fn execute(
+        &mut self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult>;
+
+    async fn commit(self: Box<Self>) -> anyhow::Result<()>;
+    async fn rollback(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QueryPlan {
+    pub nodes: Vec<PlanNode>,
+    /// totals for the whole plan, like the planning time
+    pub notes: Vec<String>,
+}
+
+/// A step of a query plan, in the database's words.
+#[derive(Debug, Clone, Default)]
+pub struct PlanNode {
+    /// `Seq Scan on User` or `SCAN User`
+    pub operation: String,
+    /// the table the step reads
+    pub table: Option<String>,
+    /// the step reads every row of `table`
+    pub full_scan: bool,
+    /// costs, row counts, timings and conditions
+    pub details: Vec<String>,
+    pub children: Vec<PlanNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub sql_type: String,
+    /// `None` when the driver has no equivalent for `sql_type`
+    pub data_type: Option<DataType>,
+    pub args: Vec<u64>,
+    pub optional: bool,
+}
@@ -0,0 +1,843 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use chrono::Utc;
+use hashbrown::HashMap;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use uuid::Uuid;
+use crate::db::backend::{Driver, TableSchema, Transaction};
+use crate::db::ast::{Model, ModelField, Query, Timestamp};
+use crate::db::compile;
+use crate::db::log::QueryLog;
+use crate::db::parser::qql::{Action, Aggregate, BinaryOp, Expr, OrderBy, Quantifier, Selector, UnaryOp};
+use crate::db::types::{DataType, DecimalType};
+use crate::db::value::{QueryResult, Value};
+use crate::parser::Ident;
+
+type Row = HashMap<String, Value>;
+
+/// Keeps every table in process memory, each driver starts out empty.
+/// Tables are created on first use, so migrating is optional.
+#[derive(Default)]
+pub struct MemoryDriver {
+    pub(crate) tables: Arc<Mutex<Tables>>,
+    pub(crate) log: Arc<QueryLog>,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Tables {
+    inner: HashMap<Ident, Vec<Row>>,
+}
+
+impl MemoryDriver {
+    pub fn new(log: Arc<QueryLog>) -> Self {
+        Self {
+            tables: Arc::default(),
+            log,
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for MemoryDriver {
+    async fn migrate_up(&self, model: &Model) -> anyhow::Result<()> {
+        self.tables.lock().await.inner.entry(model.name.clone()).or_default();
+        Ok(())
+    }
+
+    async fn migrate_down(&self, model: &Model) -> anyhow::Result<()> {
+        self.tables.lock().await.inner.remove(&model.name);
+        Ok(())
+    }
+
+    async fn introspect(&self) -> anyhow::Result<Vec<TableSchema>> {
+        bail!("the memory driver has no schema to introspect")
+    }
+
+    async fn execute(
+        &self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult> {
+        let mut tables = self.tables.lock().await;
+        let started = Instant::now();
+        let result = tables.execute(models, query, args);
+        self.log.record(&query.name, None, args, result.as_ref().map(|result| result.affected), started.elapsed());
+        result
+    }
+
+    async fn begin(&self) -> anyhow::Result<Box<dyn Transaction>> {
+        let started = Instant::now();
+        let tables = self.tables.clone().lock_owned().await;
+        let backup = Some(tables.clone());
+        self.log.record_statement("begin", None, Ok(()), started.elapsed());
+        Ok(Box::new(MemoryTransaction { tables, backup, log: self.log.clone() }))
+    }
+}
+
+/// Holds the tables for its whole lifetime and restores the copy taken at `begin` on rollback.
+pub struct MemoryTransaction {
+    tables: OwnedMutexGuard<Tables>,
+    /// `None` once the transaction has been committed or rolled back
+    backup: Option<Tables>,
+    log: Arc<QueryLog>,
+}
+
+#[async_trait]
+impl Transaction for MemoryTransaction {
+    async fn execute(
+        &mut self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult> {
+        let started = Instant::now();
+        let result = self.tables.execute(models, query, args);
+        self.log.record(&query.name, None, args, result.as_ref().map(|result| result.affected), started.elapsed());
+        result
+    }
+
+    async fn commit(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.backup = None;
+        self.log.record_statement("commit", None, Ok(()), Duration::ZERO);
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> anyhow::Result<()> {
+        // the backup is restored when the transaction drops
+        self.log.record_statement("rollback", None, Ok(()), Duration::ZERO);
+        Ok(())
+    }
+}
+
+impl Drop for MemoryTransaction {
+    fn drop(&mut self) {
+        if let Some(backup) = self.backup.take() {
+            *self.tables = backup;
+        }
+    }
+}
+
+impl Tables {
+    fn execute(&mut self, models: &HashMap<Ident, Model>, query: &Query, args: &[Value]) -> anyhow::Result<QueryResult> {
+        if args.len() != query.args.len() {
+            bail!("query {} expects {} arguments, found {}", query.name, query.args.len(), args.len());
+        }
+
+        let scope = Scope {
+            models,
+            query,
+            args,
+            rows: Vec::new(),
+            group: None,
+        };
+        match query.statement.action {
+            Action::Select => self.select(models, scope),
+            Action::Insert => self.insert(models, scope),
+            Action::Update => self.update(models, scope),
+            Action::Delete => self.delete(scope),
+        }
+    }
+
+    fn select(&self, models: &HashMap<Ident, Model>, scope: Scope) -> anyhow::Result<QueryResult> {
+        let statement = &scope.query.statement;
+
+        let mut columns = Vec::<(&Ident, &Ident, Option<Aggregate>)>::new();
+        let mut tables = Vec::new();
+        for selector in &statement.selectors {
+            let model = model(models, &selector.name)?;
+            if selector.fields.is_empty() {
+                columns.extend(model.fields.iter().map(|f| (&selector.name, &f.name, None)));
+            } else {
+                columns.extend(selector.fields.iter().map(|f| (&selector.name, &f.name, f.aggregate)));
+            }
+            tables.push((&selector.name, self.table(&selector.name)));
+        }
+        let grouped = !statement.group_by.is_empty()
+            || statement.having.is_some()
+            || columns.iter().any(|(_, _, aggregate)| aggregate.is_some());
+
+        let limit = match &statement.quantifier {
+            Quantifier::One => Some(1),
+            Quantifier::All => None,
+            Quantifier::Number(n) => Some(usize::try_from(*n)?),
+            Quantifier::Expr(expr) => match scope.eval(expr)? {
+                Value::Int(n) => Some(usize::try_from(n)?),
+                value => bail!("query {} has a non-integer quantifier: {value}", scope.query.name),
+            },
+        };
+        let offset = match &statement.offset {
+            None => 0,
+            Some(expr) => match scope.eval(expr)? {
+                Value::Int(n) => usize::try_from(n)?,
+                value => bail!("query {} has a non-integer offset: {value}", scope.query.name),
+            },
+        };
+        let cursor = compile::cursor(models, scope.query, scope.args)?;
+        // ordered and grouped rows are only known once every row has been seen
+        let wanted = match statement.order_by.is_empty() && !grouped {
+            true => limit.map(|limit| limit + offset),
+            false => None,
+        };
+
+        let mut out = Vec::new();
+        let mut groups = Vec::<(Vec<Value>, Vec<Scope>)>::new();
+        // walk the cartesian product of every selected table, like an odometer
+        let mut indices = vec![0usize; tables.len()];
+        let mut done = tables.iter().any(|(_, rows)| rows.is_empty());
+        while !done && wanted.is_none_or(|wanted| out.len() < wanted) {
+            let rows = tables.iter()
+                .zip(&indices)
+                .map(|((name, rows), &i)| (*name, &rows[i]))
+                .collect::<Vec<_>>();
+            let scope = Scope { rows, ..scope.clone() };
+
+            let keys = statement.order_by.iter()
+                .map(|order| scope.field(order.model.as_ref(), &order.field))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let after_cursor = match &cursor {
+                Some(cursor) => after(&keys, cursor, &statement.order_by)?,
+                None => true,
+            };
+
+            if after_cursor && scope.matches()? && grouped {
+                let key = statement.group_by.iter()
+                    .map(|group| scope.field(group.model.as_ref(), &group.field))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                match groups.iter_mut().find(|(group, _)| *group == key) {
+                    Some((_, scopes)) => scopes.push(scope),
+                    None => groups.push((key, vec![scope])),
+                }
+            } else if after_cursor && scope.matches()? {
+                let row = columns.iter()
+                    .map(|(model, field, _)| {
+                        let value = scope.field(Some(model), field)?;
+                        let type_ = &scope.model_field(Some(model), field)?.repr;
+                        type_.decode(value).map_err(|e| anyhow!(e))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                out.push((keys, row));
+            }
+
+            done = true;
+            for position in (0..indices.len()).rev() {
+                indices[position] += 1;
+                if indices[position] < tables[position].1.len() {
+                    done = false;
+                    break;
+                }
+                indices[position] = 0;
+            }
+        }
+
+        // like sql, aggregating without GROUP BY gives one row even when nothing matches
+        if grouped && statement.group_by.is_empty() && groups.is_empty() {
+            groups.push((Vec::new(), Vec::new()));
+        }
+        for (_, group) in &groups {
+            let scope = Scope {
+                rows: group.first().map(|scope| scope.rows.clone()).unwrap_or_default(),
+                group: Some(group),
+                ..scope.clone()
+            };
+            if let Some(having) = &statement.having {
+                if scope.eval(having)? != Value::Bool(true) {
+                    continue;
+                }
+            }
+
+            let keys = statement.order_by.iter()
+                .map(|order| scope.field(order.model.as_ref(), &order.field))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let row = columns.iter()
+                .map(|(model, field, aggregate)| {
+                    let (value, type_) = match aggregate {
+                        Some(aggregate) => (
+                            scope.aggregate(*aggregate, Some(model), field)?,
+                            compile::aggregate_type(*aggregate, scope.model_field(Some(model), field)?),
+                        ),
+                        None => (scope.field(Some(model), field)?, scope.model_field(Some(model), field)?.repr.clone()),
+                    };
+                    type_.decode(value).map_err(|e| anyhow!(e))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            out.push((keys, row));
+        }
+
+        // stable, so rows with equal keys keep the order of their tables
+        out.sort_by(|(lhs, _), (rhs, _)| order(lhs, rhs, &statement.order_by));
+        let out = out.into_iter()
+            .map(|(_, row)| row)
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+
+        Ok(QueryResult {
+            columns: columns.iter()
+                .map(|(_, field, aggregate)| match aggregate {
+                    Some(aggregate) => aggregate.column(field),
+                    None => field.to_string(),
+                })
+                .collect(),
+            affected: out.len() as u64,
+            rows: out,
+        })
+    }
+
+    fn insert(&mut self, models: &HashMap<Ident, Model>, scope: Scope) -> anyhow::Result<QueryResult> {
+        let selector = single_selector(scope.query)?;
+        let model = model(models, &selector.name)?;
+
+        let mut row = Row::new();
+        for field in &selector.fields {
+            let value = match &field.value {
+                Some(expr) => scope.eval(expr)?,
+                None => Value::Null,
+            };
+            row.insert(field.name.to_string(), value);
+        }
+        for field in &model.fields {
+            let value = row.remove(field.name.as_str())
+                .or_else(|| field.default_value())
+                .unwrap_or(Value::Null);
+            let value = match (field.repr.data_type(), value) {
+                (DataType::UUID, Value::Null) => Value::Uuid(Uuid::new_v4()),
+                (_, value) => value,
+            };
+            row.insert(field.name.to_string(), check(model, field, value)?);
+        }
+
+        self.table_mut(&selector.name).push(row);
+        Ok(QueryResult {
+            affected: 1,
+            ..QueryResult::default()
+        })
+    }
+
+    fn update(&mut self, models: &HashMap<Ident, Model>, scope: Scope) -> anyhow::Result<QueryResult> {
+        let selector = single_selector(scope.query)?;
+        let model = model(models, &selector.name)?;
+
+        // compute every change before applying any, so a failing row leaves the table untouched
+        let table = self.table(&selector.name);
+        let mut updates = Vec::new();
+        for (i, row) in table.iter().enumerate() {
+            let scope = Scope { rows: vec![(&selector.name, row)], ..scope.clone() };
+            if !scope.matches()? {
+                continue;
+            }
+
+            let mut changes = Vec::new();
+            for field in &selector.fields {
+                let Some(expr) = &field.value else {
+                    continue;
+                };
+                let model_field = model.get_field(&field.name)
+                    .ok_or_else(|| anyhow!("{} has no field {:?}", model.name, field.name))?;
+                let value = check(model, model_field, scope.eval(expr)?)?;
+                changes.push((field.name.to_string(), value));
+            }
+            for field in &model.fields {
+                if field.timestamp != Some(Timestamp::UpdatedAt) || selector.fields.iter().any(|f| f.name == field.name) {
+                    continue;
+                }
+                let value = Value::DateTime(Utc::now().naive_utc());
+                changes.push((field.name.to_string(), value));
+            }
+            updates.push((i, changes));
+        }
+
+        let table = self.table_mut(&selector.name);
+        let affected = updates.len() as u64;
+        for (i, changes) in updates {
+            table[i].extend(changes);
+        }
+
+        Ok(QueryResult {
+            affected,
+            ..QueryResult::default()
+        })
+    }
+
+    fn delete(&mut self, scope: Scope) -> anyhow::Result<QueryResult> {
+        let selector = single_selector(scope.query)?;
+
+        let table = self.table_mut(&selector.name);
+        let mut error = None;
+        let before = table.len();
+        table.retain(|row| {
+            let scope = Scope { rows: vec![(&selector.name, row)], ..scope.clone() };
+            match scope.matches() {
+                Ok(matches) => !matches,
+                Err(e) => {
+                    error.get_or_insert(e);
+                    true
+                }
+            }
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        Ok(QueryResult {
+            affected: (before - table.len()) as u64,
+            ..QueryResult::default()
+        })
+    }
+
+    fn table(&self, name: &Ident) -> &[Row] {
+        self.inner.get(name)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn table_mut(&mut self, name: &Ident) -> &mut Vec<Row> {
+        self.inner.entry(name.clone()).or_default()
+    }
+}
+
+fn model<'a>(models: &'a HashMap<Ident, Model>, name: &Ident) -> anyhow::Result<&'a Model> {
+    models.get(name)
+        .ok_or_else(|| anyhow!("unknown model {:?}", name))
+}
+
+fn single_selector(query: &Query) -> anyhow::Result<&Selector> {
+    match query.statement.selectors.as_slice() {
+        [selector] => Ok(selector),
+        _ => bail!("query {} must use exactly one model", query.name),
+    }
+}
+
+/// converts `value` to the field's representation and enforces the constraints a database would
+fn check(model: &Model, field: &ModelField, value: Value) -> anyhow::Result<Value> {
+    if value.is_null() && !field.optional {
+        bail!("null value in {}.{} violates not-null constraint", model.name, field.name);
+    }
+
+    let value = value.coerce(field.repr.data_type())
+        .map_err(|e| anyhow!("invalid value for {}.{}: {e}", model.name, field.name))?
+        .into_owned();
+    let value = match (value, field.args.as_slice()) {
+        (Value::String(value), [max]) if value.chars().count() as u64 > *max => {
+            bail!("value too long for {}.{}, maximum length is {max}", model.name, field.name);
+        }
+        (Value::Bytes(value), [max]) if value.len() as u64 > *max => {
+            bail!("value too long for {}.{}, maximum length is {max}", model.name, field.name);
+        }
+        (Value::Decimal(value), args) => DecimalType::fit(value, args)
+            .map(Value::Decimal)
+            .map_err(|e| anyhow!("{e} in {}.{}", model.name, field.name))?,
+        (value, _) => value,
+    };
+    field.repr.validate(&value)
+        .map_err(|e| anyhow!("invalid value for {}.{}: {e}", model.name, field.name))?;
+    field.repr.encode(value)
+        .map_err(|e| anyhow!("unable to store {}.{}: {e}", model.name, field.name))
+}
+
+#[derive(Clone)]
+struct Scope<'a> {
+    models: &'a HashMap<Ident, Model>,
+    query: &'a Query,
+    args: &'a [Value],
+    rows: Vec<(&'a Ident, &'a Row)>,
+    /// every row of the group being aggregated, `rows` is the first of them
+    group: Option<&'a [Scope<'a>]>,
+}
+
+impl<'a> Scope<'a> {
+    fn matches(&self) -> anyhow::Result<bool> {
+        match &self.query.statement.where_clause {
+            Some(where_clause) => Ok(self.eval(&where_clause.expr)? == Value::Bool(true)),
+            None => Ok(true),
+        }
+    }
+
+    fn field(&self, model: Option<&Ident>, field: &Ident) -> anyhow::Result<Value> {
+        self.rows.iter()
+            .filter(|(name, _)| model.is_none_or(|model| model == *name))
+            .find_map(|(_, row)| row.get(field.as_str()))
+            .cloned()
+            .ok_or_else(|| anyhow!("query {} uses unknown field {}", self.query.name, field))
+    }
+
+    /// the field a column comes from, like `field` the first selected model with it wins
+    fn model_field(&self, model: Option<&Ident>, field: &Ident) -> anyhow::Result<&'a ModelField> {
+        self.query.statement.selectors.iter()
+            .filter(|selector| model.is_none_or(|model| *model == selector.name))
+            .find_map(|selector| self.models.get(&selector.name)?.get_field(field))
+            .ok_or_else(|| anyhow!("query {} uses unknown field {}", self.query.name, field))
+    }
+
+    /// `aggregate` of the field over every row of the group, skipping nulls like sql
+    fn aggregate(&self, aggregate: Aggregate, model: Option<&Ident>, field: &Ident) -> anyhow::Result<Value> {
+        let Some(group) = self.group else {
+            bail!("query {} uses {} outside of a grouped select", self.query.name, aggregate.function());
+        };
+        let data_type = compile::aggregate_type(aggregate, self.model_field(model, field)?).data_type();
+        let values = group.iter()
+            .map(|scope| scope.field(model, field))
+            .filter(|value| !value.as_ref().is_ok_and(Value::is_null))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if aggregate == Aggregate::Count {
+            return Ok(Value::Int(values.len() as i64));
+        }
+
+        let mut result = None::<Value>;
+        for value in &values {
+            result = Some(match (aggregate, result) {
+                (Aggregate::Min | Aggregate::Max, None) => value.clone(),
+                (Aggregate::Min | Aggregate::Max, Some(best)) => {
+                    let ordering = compare(value, &best)
+                        .ok_or_else(|| anyhow!("cannot compare {value} with {best}"))?;
+                    let better = if aggregate == Aggregate::Min { ordering.is_lt() } else { ordering.is_gt() };
+                    if better { value.clone() } else { best }
+                }
+                (_, total) => {
+                    let value = value.coerce(data_type).map_err(|e| anyhow!(e))?.into_owned();
+                    match total {
+                        Some(total) => binary(&BinaryOp::Add, total, value)?,
+                        None => value,
+                    }
+                }
+            });
+        }
+
+        Ok(match (aggregate, result) {
+            (_, None) => Value::Null,
+            (Aggregate::Avg, Some(total)) => binary(&BinaryOp::Div, total, Value::Int(values.len() as i64))?,
+            (_, Some(result)) => result,
+        })
+    }
+
+    /// `field == #argument` where the field's type compares values itself, see `Type::verify`
+    fn compared_in_rust(&self, lhs: &Expr, rhs: &Expr) -> anyhow::Result<bool> {
+        match (lhs, rhs) {
+            (Expr::Field(model, field), Expr::Interp(_)) | (Expr::Interp(_), Expr::Field(model, field)) => {
+                Ok(self.model_field(model.as_ref(), field)?.repr.compared_in_rust())
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn eval(&self, expr: &Expr) -> anyhow::Result<Value> {
+        match expr {
+            Expr::Binary(lhs, BinaryOp::Eq, rhs) if self.compared_in_rust(lhs, rhs)? => {
+                let ((Expr::Field(model, field), arg) | (arg, Expr::Field(model, field))) = (lhs.as_ref(), rhs.as_ref()) else {
+                    unreachable!()
+                };
+                let stored = self.field(model.as_ref(), field)?;
+                let value = self.eval(arg)?;
+                if stored.is_null() || value.is_null() {
+                    return Ok(Value::Null);
+                }
+                let type_ = &self.model_field(model.as_ref(), field)?.repr;
+                type_.verify(&stored, &value).map(Value::Bool).map_err(|e| anyhow!(e))
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                binary(op, lhs, rhs)
+            }
+            Expr::Unary(op, rhs) => match (op, self.eval(rhs)?) {
+                (_, Value::Null) => Ok(Value::Null),
+                (UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+                (UnaryOp::Negative, Value::Int(value)) => value.checked_neg().map(Value::Int).ok_or_else(overflow),
+                (op, value) => bail!("cannot apply {op:?} to {value}"),
+            },
+            Expr::Number(n) => Ok(Value::Int(i64::try_from(*n)?)),
+            Expr::String(value) => Ok(Value::String(value.clone())),
+            Expr::Bool(value) => Ok(Value::Bool(*value)),
+            Expr::Null => Ok(Value::Null),
+            Expr::In(lhs, values) => {
+                let values = values.iter()
+                    .map(|value| self.eval(value))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                member(self.eval(lhs)?, values)
+            }
+            Expr::InArgument(lhs, arg) => {
+                let data_type = match lhs.as_ref() {
+                    Expr::Field(model, field) => Some(self.model_field(model.as_ref(), field)?.repr.data_type()),
+                    _ => None,
+                };
+                let value = self.eval(&Expr::Interp(arg.clone()))?;
+                let values = compile::list_argument(&value, data_type)
+                    .map_err(|e| anyhow!("argument {arg} of query {} {e}", self.query.name))?;
+                member(self.eval(lhs)?, values)
+            }
+            Expr::IsNull(inner, negated) => Ok(Value::Bool(self.eval(inner)?.is_null() != *negated)),
+            Expr::Between(inner, low, high) => {
+                let value = self.eval(inner)?;
+                let low = binary(&BinaryOp::Ge, value.clone(), self.eval(low)?)?;
+                let high = binary(&BinaryOp::Le, value, self.eval(high)?)?;
+                binary(&BinaryOp::And, low, high)
+            }
+            Expr::Interp(arg) => {
+                let index = self.query.args.iter()
+                    .position(|a| a == arg)
+                    .ok_or_else(|| anyhow!("query {} has no argument {:?}", self.query.name, arg))?;
+                Ok(self.args[index].clone())
+            }
+            Expr::Field(model, field) => self.field(model.as_ref(), field),
+            Expr::Variant(_, variant) => Ok(Value::String(variant.to_string())),
+            Expr::Aggregate(aggregate, model, field) => self.aggregate(*aggregate, model.as_ref(), field),
+            Expr::Group(inner) => self.eval(inner),
+        }
+    }
+}
+
+fn binary(op: &BinaryOp, lhs: Value, rhs: Value) -> anyhow::Result<Value> {
+    // three-valued logic, like sql
+    match (op, &lhs, &rhs) {
+        (BinaryOp::And, Value::Bool(false), _) | (BinaryOp::And, _, Value::Bool(false)) => {
+            return Ok(Value::Bool(false));
+        }
+        (BinaryOp::Or, Value::Bool(true), _) | (BinaryOp::Or, _, Value::Bool(true)) => {
+            return Ok(Value::Bool(true));
+        }
+        (_, Value::Null, _) | (_, _, Value::Null) => return Ok(Value::Null),
+        _ => {}
+    }
+
+    let value = match (op, lhs, rhs) {
+        (BinaryOp::And | BinaryOp::Or, Value::Bool(lhs), Value::Bool(rhs)) => {
+            Value::Bool(if matches!(op, BinaryOp::And) { lhs && rhs } else { lhs || rhs })
+        }
+        (BinaryOp::Like, Value::String(lhs), Value::String(rhs)) => Value::Bool(like(&lhs, &rhs)),
+        (BinaryOp::Add, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.checked_add(rhs).ok_or_else(overflow)?),
+        (BinaryOp::Sub, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.checked_sub(rhs).ok_or_else(overflow)?),
+        (BinaryOp::Mul, Value::Int(lhs), Value::Int(rhs)) => Value::Int(lhs.checked_mul(rhs).ok_or_else(overflow)?),
+        (BinaryOp::Div, Value::Int(lhs), Value::Int(rhs)) => {
+            Value::Int(lhs.checked_div(rhs).ok_or_else(|| anyhow!("division by zero"))?)
+        }
+        (BinaryOp::Rem, Value::Int(lhs), Value::Int(rhs)) => {
+            Value::Int(lhs.checked_rem(rhs).ok_or_else(|| anyhow!("division by zero"))?)
+        }
+        (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge | BinaryOp::Eq | BinaryOp::Ne, lhs, rhs) => {
+            let ordering = compare(&lhs, &rhs)
+                .ok_or_else(|| anyhow!("cannot compare {lhs} with {rhs}"))?;
+            Value::Bool(match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                BinaryOp::Ge => ordering.is_ge(),
+                BinaryOp::Eq => ordering.is_eq(),
+                _ => ordering.is_ne(),
+            })
+        }
+        (op, lhs @ (Value::Float(_) | Value::Decimal(_) | Value::Int(_)), rhs @ (Value::Float(_) | Value::Decimal(_) | Value::Int(_))) => {
+            // integers take the type of the other operand, mixing floats and decimals gives a float
+            let data_type = match (&lhs, &rhs) {
+                (Value::Float(_), _) | (_, Value::Float(_)) => DataType::Float,
+                _ => DataType::Decimal,
+            };
+            let lhs = lhs.coerce(data_type).map_err(|e| anyhow!(e))?;
+            let rhs = rhs.coerce(data_type).map_err(|e| anyhow!(e))?;
+            match (lhs.as_ref(), rhs.as_ref()) {
+                (Value::Float(lhs), Value::Float(rhs)) => Value::Float(match op {
+                    BinaryOp::Add => lhs + rhs,
+                    BinaryOp::Sub => lhs - rhs,
+                    BinaryOp::Mul => lhs * rhs,
+                    BinaryOp::Div => lhs / rhs,
+                    BinaryOp::Rem => lhs % rhs,
+                    op => bail!("cannot apply {op:?} to {lhs} and {rhs}"),
+                }),
+                (Value::Decimal(lhs), Value::Decimal(rhs)) => Value::Decimal(match op {
+                    BinaryOp::Add => lhs.checked_add(*rhs).ok_or_else(overflow)?,
+                    BinaryOp::Sub => lhs.checked_sub(*rhs).ok_or_else(overflow)?,
+                    BinaryOp::Mul => lhs.checked_mul(*rhs).ok_or_else(overflow)?,
+                    BinaryOp::Div => lhs.checked_div(*rhs).ok_or_else(|| anyhow!("division by zero"))?,
+                    BinaryOp::Rem => lhs.checked_rem(*rhs).ok_or_else(|| anyhow!("division by zero"))?,
+                    op => bail!("cannot apply {op:?} to {lhs} and {rhs}"),
+                }),
+                _ => unreachable!(),
+            }
+        }
+        (op, lhs, rhs) => bail!("cannot apply {op:?} to {lhs} and {rhs}"),
+    };
+    Ok(value)
+}
+
+/// like postgres, nulls come after every value in ascending order
+fn order(lhs: &[Value], rhs: &[Value], order_by: &[OrderBy]) -> Ordering {
+    for ((lhs, rhs), order) in lhs.iter().zip(rhs).zip(order_by) {
+        let ordering = match (lhs, rhs) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Greater,
+            (_, Value::Null) => Ordering::Less,
+            (lhs, rhs) => compare(lhs, rhs).unwrap_or(Ordering::Equal),
+        };
+        let ordering = if order.descending { ordering.reverse() } else { ordering };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// the keyset comparison the sql drivers compile for `AFTER`, with the same handling of nulls
+fn after(keys: &[Value], cursor: &[Value], order_by: &[OrderBy]) -> anyhow::Result<bool> {
+    let mut passed = Value::Bool(false);
+    let mut equal = Value::Bool(true);
+    for ((key, value), order) in keys.iter().zip(cursor).zip(order_by) {
+        let op = if order.descending { BinaryOp::Lt } else { BinaryOp::Gt };
+        let past = binary(&op, key.clone(), value.clone())?;
+        passed = binary(&BinaryOp::Or, passed, binary(&BinaryOp::And, equal.clone(), past)?)?;
+        equal = binary(&BinaryOp::And, equal, binary(&BinaryOp::Eq, key.clone(), value.clone())?)?;
+    }
+    Ok(passed == Value::Bool(true))
+}
+
+/// `lhs IN (values)`, null unless a value matches when either side has a null, like sql
+fn member(lhs: Value, values: Vec<Value>) -> anyhow::Result<Value> {
+    let mut result = Value::Bool(false);
+    for value in values {
+        result = binary(&BinaryOp::Or, result, binary(&BinaryOp::Eq, lhs.clone(), value)?)?;
+    }
+    Ok(result)
+}
+
+/// sql `LIKE`, case sensitive and with `\` as the escape character like the compiled queries
+fn like(value: &str, pattern: &str) -> bool {
+    fn matches(value: &[char], pattern: &[char]) -> bool {
+        match pattern {
+            [] => value.is_empty(),
+            ['%', rest @ ..] => (0..=value.len()).any(|i| matches(&value[i..], rest)),
+            ['_', rest @ ..] => !value.is_empty() && matches(&value[1..], rest),
+            ['\\', c, rest @ ..] | [c, rest @ ..] => value.first() == Some(c) && matches(&value[1..], rest),
+        }
+    }
+
+    let value = value.chars().collect::<Vec<_>>();
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    matches(&value, &pattern)
+}
+
+fn overflow() -> anyhow::Error {
+    anyhow!("integer overflow")
+}
+
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Bool(lhs), Value::Bool(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Int(lhs), Value::Int(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Uuid(lhs), Value::Uuid(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::DateTime(lhs), Value::DateTime(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Float(lhs), Value::Float(rhs)) => lhs.partial_cmp(rhs),
+        (Value::Decimal(lhs), Value::Decimal(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Date(lhs), Value::Date(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Time(lhs), Value::Time(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Duration(lhs), Value::Duration(rhs)) => Some(lhs.cmp(rhs)),
+        (Value::Bytes(lhs), Value::Bytes(rhs)) => Some(lhs.cmp(rhs)),
+        // json has no natural order, objects serialize with sorted keys so equal values compare equal
+        (Value::Json(lhs), Value::Json(rhs)) => Some(lhs.to_string().cmp(&rhs.to_string())),
+        (Value::Int(_), Value::Float(_) | Value::Decimal(_)) | (Value::Decimal(_), Value::Float(_)) => {
+            compare(rhs, lhs).map(Ordering::reverse)
+        }
+        // strings are parsed as the other operand's type, like a literal in sql
+        (Value::String(_), _) => compare(rhs, lhs).map(Ordering::reverse),
+        (lhs, rhs) => {
+            let rhs = rhs.coerce(lhs.data_type()?).ok()?;
+            compare(lhs, &rhs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Context;
+    use crate::db::parser::QQLFile;
+    use crate::db::validate;
+    use crate::db::value::Value;
+
+    const SOURCE: &str = r#"
+        model Note {
+            note_id: Int @primary,
+            title: String,
+            views: Int
+        }
+
+        query AddNote(note_id, title, views) {
+            INSERT ONE Note(note_id = #note_id, title = #title, views = #views)
+        }
+
+        query Popular(views) {
+            SELECT ALL Note(note_id, title) WHERE views >= #views ORDER BY note_id
+        }
+
+        query Unpopular(views) {
+            SELECT ALL Note(note_id) WHERE views < -#views
+        }
+
+        query Rename(note_id, title) {
+            UPDATE ALL Note(title = #title) WHERE note_id == #note_id
+        }
+
+        query Remove(views) {
+            DELETE ALL Note() WHERE views < #views
+        }
+    "#;
+
+    fn context() -> Context {
+        let context = Context::memory();
+        let file = SOURCE.parse::<QQLFile>().unwrap();
+        validate::validate_file(&context, "", &file).unwrap();
+        context
+    }
+
+    async fn add(context: &Context, note_id: i64, title: &str, views: i64) {
+        let args = [Value::Int(note_id), Value::String(title.to_owned()), Value::Int(views)];
+        assert_eq!(context.execute("AddNote", &args).await.unwrap().affected, 1);
+    }
+
+    fn note(note_id: i64, title: &str) -> Vec<Value> {
+        vec![Value::Int(note_id), Value::String(title.to_owned())]
+    }
+
+    #[tokio::test]
+    async fn writes_are_seen_by_where() {
+        let context = context();
+        add(&context, 1, "first", 10).await;
+        add(&context, 2, "second", 0).await;
+        add(&context, 3, "third", 5).await;
+
+        let popular = context.execute("Popular", &[Value::Int(5)]).await.unwrap();
+        assert_eq!(popular.columns, ["note_id", "title"]);
+        assert_eq!(popular.rows, [note(1, "first"), note(3, "third")]);
+
+        let renamed = context.execute("Rename", &[Value::Int(3), Value::String("renamed".to_owned())]).await.unwrap();
+        assert_eq!(renamed.affected, 1);
+        let popular = context.execute("Popular", &[Value::Int(0)]).await.unwrap();
+        assert_eq!(popular.rows, [note(1, "first"), note(2, "second"), note(3, "renamed")]);
+
+        let removed = context.execute("Remove", &[Value::Int(6)]).await.unwrap();
+        assert_eq!(removed.affected, 2);
+        let popular = context.execute("Popular", &[Value::Int(0)]).await.unwrap();
+        assert_eq!(popular.rows, [note(1, "first")]);
+    }
+
+    #[tokio::test]
+    async fn each_context_starts_empty() {
+        let first = context();
+        add(&first, 1, "first", 10).await;
+        assert_eq!(first.execute("Popular", &[Value::Int(0)]).await.unwrap().rows.len(), 1);
+
+        let second = context();
+        assert!(second.execute("Popular", &[Value::Int(0)]).await.unwrap().rows.is_empty());
+        add(&second, 1, "same key", 10).await;
+    }
+
+    #[tokio::test]
+    async fn negating_the_smallest_int_fails() {
+        let context = context();
+        add(&context, 1, "first", 10).await;
+        let unpopular = context.execute("Unpopular", &[Value::Int(-20)]).await.unwrap();
+        assert_eq!(unpopular.rows, [vec![Value::Int(1)]]);
+        let error = context.execute("Unpopular", &[Value::Int(i64::MIN)]).await.unwrap_err();
+        assert_eq!(error.to_string(), "integer overflow");
+    }
+}
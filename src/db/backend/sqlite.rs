@@ -0,0 +1,574 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use anyhow::{anyhow, bail, Context as _Context};
+use async_trait::async_trait;
+use chrono::TimeDelta;
+use hashbrown::HashMap;
+use rusqlite::Connection;
+use tokio::sync::{mpsc, Mutex, OwnedMutexGuard};
+use tokio::task::spawn_blocking;
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use uuid::Uuid;
+use crate::db::backend::{ColumnSchema, Driver, PlanNode, QueryPlan, TableSchema, Transaction};
+use crate::db::ast::{Index, Model, ModelField, Query};
+use crate::db::compile::{compile, CompiledQuery, Dialect};
+use crate::db::log::QueryLog;
+use crate::db::quote::Quoted;
+use crate::db::types::{DataType, DecimalType, DriverKind};
+use crate::db::value::{QueryResult, Value};
+use crate::parser::Ident;
+
+/// SQLite only allows one writer at a time, so every statement goes through a single connection.
+/// rusqlite blocks, so the connection is only locked and used on tokio's blocking threads.
+pub struct SqliteDriver {
+    pub(crate) file: PathBuf,
+    pub(crate) connection: Arc<Mutex<Connection>>,
+    pub(crate) log: Arc<QueryLog>,
+}
+
+impl SqliteDriver {
+    pub fn new(file: PathBuf, log: Arc<QueryLog>) -> anyhow::Result<Self> {
+        let connection = Connection::open(&file)?;
+        // like postgres, `LIKE` is case sensitive
+        connection.pragma_update(None, "case_sensitive_like", true)?;
+        Ok(Self { file, connection: Arc::new(Mutex::new(connection)), log })
+    }
+
+    /// Runs `f` with the connection on a blocking thread.
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> anyhow::Result<T> + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let connection = self.connection.clone();
+        spawn_blocking(move || f(&connection.blocking_lock())).await?
+    }
+}
+
+#[async_trait]
+impl Driver for SqliteDriver {
+    async fn migrate_up(&self, model: &Model) -> anyhow::Result<()> {
+        use std::fmt::Write;
+
+        let mut builder = String::new();
+        write!(&mut builder, "create table if not exists {} (", Quoted(&model.name))?;
+        for (i, field) in model.fields.iter().enumerate() {
+            if i > 0 {
+                write!(&mut builder, ",")?;
+            }
+            writeln!(&mut builder)?;
+
+            let type_def = self.type_definition(field)
+                .with_context(|| format!("field {:?} has invalid type {:?}", &field.name, field.repr))?;
+            write!(&mut builder, "  {} {}", Quoted(&field.name), type_def)?;
+        }
+        write!(&mut builder, "\n);")?;
+        for field in &model.fields {
+            if let Some(statement) = field.index.and_then(|index| index.create_statement(model, field)) {
+                writeln!(&mut builder, "\n{statement};")?;
+            }
+        }
+
+        let (log, name) = (self.log.clone(), format!("migrate up {}", model.name));
+        self.blocking(move |connection| batch(connection, &log, &name, &builder)).await
+    }
+
+    async fn migrate_down(&self, model: &Model) -> anyhow::Result<()> {
+        use std::fmt::Write;
+
+        let mut builder = String::new();
+        writeln!(&mut builder, "drop table if exists {};", Quoted(&model.name))?;
+
+        let (log, name) = (self.log.clone(), format!("migrate down {}", model.name));
+        self.blocking(move |connection| batch(connection, &log, &name, &builder)).await
+    }
+
+    async fn introspect(&self) -> anyhow::Result<Vec<TableSchema>> {
+        self.blocking(introspect).await
+    }
+
+    async fn execute(
+        &self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult> {
+        let compiled = compile(&SqliteDialect, models, query, args)?;
+        let (log, name) = (self.log.clone(), query.name.to_string());
+        self.blocking(move |connection| compiled.finish(run(connection, &log, &name, &compiled)?)).await
+    }
+
+    /// The statement steps on a blocking thread and sends its rows back, once `row` fails the
+    /// channel closes and the statement stops.
+    async fn stream(
+        &self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+        row: &mut (dyn FnMut(Vec<Value>) -> anyhow::Result<()> + Send),
+    ) -> anyhow::Result<()> {
+        let compiled = compile(&SqliteDialect, models, query, args)?;
+        let (connection, log, name) = (self.connection.clone(), self.log.clone(), query.name.to_string());
+        let (sender, mut receiver) = mpsc::channel(STREAM_BUFFER);
+        let task = spawn_blocking(move || {
+            let mut send = |values| sender.blocking_send(values).map_err(|_| anyhow!("stopped reading rows"));
+            stream(&connection.blocking_lock(), &log, &name, &compiled, &mut send)
+        });
+
+        while let Some(values) = receiver.recv().await {
+            if let Err(error) = row(values) {
+                receiver.close();
+                let _ = task.await;
+                return Err(error);
+            }
+        }
+        task.await?
+    }
+
+    async fn explain(
+        &self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+        analyze: bool,
+    ) -> anyhow::Result<QueryPlan> {
+        if analyze {
+            bail!("sqlite only reports the plan of a query, explain it without analyzing");
+        }
+        let compiled = compile(&SqliteDialect, models, query, args)?;
+        let steps = self.blocking(move |connection| {
+            let mut statement = connection.prepare(&format!("explain query plan {}", compiled.sql))?;
+            let steps = statement
+                .query_map(rusqlite::params_from_iter(compiled.params.iter()), |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(3)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(steps)
+        }).await?;
+
+        Ok(QueryPlan {
+            nodes: plan_nodes(&steps, 0),
+            notes: Vec::new(),
+        })
+    }
+
+    async fn begin(&self) -> anyhow::Result<Box<dyn Transaction>> {
+        let (connection, log) = (self.connection.clone(), self.log.clone());
+        let connection = spawn_blocking(move || {
+            let connection = connection.blocking_lock_owned();
+            batch(&connection, &log, "begin", "begin")?;
+            anyhow::Ok(connection)
+        }).await??;
+        Ok(Box::new(SqliteTransaction { connection: Some(connection), log: self.log.clone(), finished: false }))
+    }
+}
+
+/// Rows sent ahead of the reader of `stream`.
+const STREAM_BUFFER: usize = 64;
+
+/// The tables in the database with their columns.
+fn introspect(connection: &Connection) -> anyhow::Result<Vec<TableSchema>> {
+    let mut statement = connection.prepare(
+        "select name from sqlite_master
+         where type = 'table' and name not like 'sqlite_%'
+         order by name",
+    )?;
+    let names = statement.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tables = Vec::new();
+    for name in names {
+        let mut statement = connection.prepare(&format!("pragma table_info({})", Quoted(&name)))?;
+        let columns = statement
+            .query_map([], |row| {
+                let sql_type: String = row.get(2)?;
+                let not_null: bool = row.get(3)?;
+                let (data_type, args) = match SqliteDriver::data_type(&sql_type) {
+                    Some((data_type, args)) => (Some(data_type), args),
+                    None => (None, Vec::new()),
+                };
+                Ok(ColumnSchema {
+                    name: row.get(1)?,
+                    sql_type,
+                    data_type,
+                    args,
+                    optional: !not_null,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        tables.push(TableSchema { name, columns });
+    }
+
+    Ok(tables)
+}
+
+/// Runs statements that are not queries, a migration or `begin`, and records them as `name`.
+fn batch(connection: &Connection, log: &QueryLog, name: &str, sql: &str) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let result = connection.execute_batch(sql).map_err(anyhow::Error::from);
+    log.record_statement(name, Some(sql), result.as_ref().map(|_| ()), started.elapsed());
+    result
+}
+
+fn run(connection: &Connection, log: &QueryLog, name: &str, compiled: &CompiledQuery) -> anyhow::Result<QueryResult> {
+    let started = Instant::now();
+    let result = fetch(connection, compiled);
+    let rows = result.as_ref().map(|result| result.affected);
+    log.record(name, Some(&compiled.sql), &compiled.params, rows, started.elapsed());
+    result
+}
+
+fn fetch(connection: &Connection, compiled: &CompiledQuery) -> anyhow::Result<QueryResult> {
+    let params = rusqlite::params_from_iter(compiled.params.iter());
+
+    if !compiled.returns_rows {
+        let affected = connection.execute(&compiled.sql, params)?;
+        return Ok(QueryResult {
+            affected: affected as u64,
+            ..QueryResult::default()
+        });
+    }
+
+    let mut statement = connection.prepare_cached(&compiled.sql)?;
+    let columns = statement.column_names()
+        .into_iter()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    let types = column_types(&statement, compiled);
+    let rows = statement
+        .query_map(params, |row| read_row(row, types.len()))?
+        .map(|row| decode_row(row?, &types))
+        .collect::<anyhow::Result<Vec<Vec<_>>>>()?;
+
+    Ok(QueryResult {
+        columns,
+        affected: rows.len() as u64,
+        rows,
+    })
+}
+
+/// The steps of `explain query plan` below `parent`, which are `(id, parent, detail)`.
+fn plan_nodes(steps: &[(i64, i64, String)], parent: i64) -> Vec<PlanNode> {
+    steps.iter()
+        .filter(|(_, step_parent, _)| *step_parent == parent)
+        .map(|(id, _, detail)| {
+            // `SCAN User` reads the whole table, `SEARCH User USING INDEX ..` does not
+            let mut words = detail.split_whitespace();
+            let verb = words.next().unwrap_or_default();
+            let table = matches!(verb, "SCAN" | "SEARCH")
+                .then(|| words.next().map(|table| table.trim_matches('"').to_owned()))
+                .flatten();
+            PlanNode {
+                operation: detail.clone(),
+                full_scan: verb == "SCAN" && !detail.contains(" USING "),
+                table,
+                details: Vec::new(),
+                children: plan_nodes(steps, *id),
+            }
+        })
+        .collect()
+}
+
+/// Like `run` for a select, handing rows to `row` while the statement steps through them.
+fn stream(
+    connection: &Connection,
+    log: &QueryLog,
+    name: &str,
+    compiled: &CompiledQuery,
+    row: &mut (dyn FnMut(Vec<Value>) -> anyhow::Result<()> + Send),
+) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let result = step(connection, compiled, row);
+    log.record(name, Some(&compiled.sql), &compiled.params, result.as_ref().copied(), started.elapsed());
+    result.map(|_| ())
+}
+
+/// Hands the rows `compiled` selects to `row`, returns how many it accepted.
+fn step(
+    connection: &Connection,
+    compiled: &CompiledQuery,
+    row: &mut (dyn FnMut(Vec<Value>) -> anyhow::Result<()> + Send),
+) -> anyhow::Result<u64> {
+    let mut statement = connection.prepare_cached(&compiled.sql)?;
+    let types = column_types(&statement, compiled);
+    let mut rows = statement.query(rusqlite::params_from_iter(compiled.params.iter()))?;
+    let mut filter = compiled.row_filter();
+    let mut count = 0;
+    while let Some(stored) = rows.next()? {
+        if filter.done() {
+            break;
+        }
+        let values = decode_row(read_row(stored, types.len())?, &types)?;
+        if let Some(values) = filter.accept(values)? {
+            row(values)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// sqlite only has a handful of storage classes, the declared type says how to read them back
+fn column_types(statement: &rusqlite::Statement, compiled: &CompiledQuery) -> Vec<Option<(DataType, Vec<u64>)>> {
+    statement.columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| column.decl_type()
+            .and_then(SqliteDriver::data_type)
+            // computed columns such as aggregates have no declared type
+            .or_else(|| compiled.columns.get(i).map(|type_| (type_.data_type(), Vec::new()))))
+        .collect()
+}
+
+fn read_row(row: &rusqlite::Row, columns: usize) -> rusqlite::Result<Vec<Value>> {
+    (0..columns).map(|i| row.get::<_, Value>(i)).collect()
+}
+
+fn decode_row(row: Vec<Value>, types: &[Option<(DataType, Vec<u64>)>]) -> anyhow::Result<Vec<Value>> {
+    row.into_iter()
+        .zip(types)
+        .map(|(value, type_)| match type_ {
+            Some((data_type, args)) => decode(value, *data_type, args),
+            None => Ok(value),
+        })
+        .collect()
+}
+
+/// Holds the connection for its whole lifetime, other statements wait until it finishes.
+pub struct SqliteTransaction {
+    /// only `None` while a statement runs on a blocking thread
+    connection: Option<OwnedMutexGuard<Connection>>,
+    log: Arc<QueryLog>,
+    finished: bool,
+}
+
+impl SqliteTransaction {
+    /// Hands the connection to a blocking thread for `f` and takes it back afterwards.
+    async fn blocking<T: Send + 'static>(
+        &mut self,
+        f: impl FnOnce(&Connection) -> anyhow::Result<T> + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let connection = self.connection.take()
+            .ok_or_else(|| anyhow!("the transaction lost its connection"))?;
+        let (connection, result) = spawn_blocking(move || {
+            let result = f(&connection);
+            (connection, result)
+        }).await?;
+        self.connection = Some(connection);
+        result
+    }
+
+    async fn finish(&mut self, statement: &'static str) -> anyhow::Result<()> {
+        self.finished = true;
+        let log = self.log.clone();
+        self.blocking(move |connection| batch(connection, &log, statement, statement)).await
+    }
+}
+
+#[async_trait]
+impl Transaction for SqliteTransaction {
+    async fn execute(
+        &mut self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult> {
+        let compiled = compile(&SqliteDialect, models, query, args)?;
+        let (log, name) = (self.log.clone(), query.name.to_string());
+        self.blocking(move |connection| compiled.finish(run(connection, &log, &name, &compiled)?)).await
+    }
+
+    async fn commit(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.finish("commit").await
+    }
+
+    async fn rollback(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.finish("rollback").await
+    }
+}
+
+impl Drop for SqliteTransaction {
+    fn drop(&mut self) {
+        // the connection stays locked until the rollback is done, on a blocking thread when a
+        // runtime is around to spawn one
+        let Some(connection) = self.connection.take().filter(|_| !self.finished) else {
+            return;
+        };
+        let log = self.log.clone();
+        let rollback = move || {
+            let _ = batch(&connection, &log, "rollback", "rollback");
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(rollback)),
+            Err(_) => rollback(),
+        }
+    }
+}
+
+impl SqliteDriver {
+    fn type_definition(&self, type_: &ModelField) -> anyhow::Result<String> {
+        let column = Quoted(&type_.name).to_string();
+        let mut builder = match type_.repr.column_type(DriverKind::Sqlite, &column, &type_.args) {
+            Some(column_type) => column_type,
+            None => Self::column_type(type_)?,
+        };
+
+        if !type_.optional {
+            builder.push_str(" NOT NULL");
+        }
+        if type_.index == Some(Index::Primary) {
+            builder.push_str(" PRIMARY KEY");
+        }
+
+        Ok(builder)
+    }
+
+    fn column_type(type_: &ModelField) -> anyhow::Result<String> {
+        use std::fmt::Write;
+        let mut builder = String::new();
+
+        match type_.repr.data_type() {
+            // uuids are generated in rust, see `generated_value`
+            DataType::UUID => builder.push_str("char(36)"),
+            DataType::String => match type_.args.as_slice() {
+                [max] => write!(&mut builder, "varchar({max}) check (length({}) <= {max})", Quoted(&type_.name))?,
+                _ => builder.push_str("text"),
+            },
+            DataType::DateTime => builder.push_str("datetime"),
+            DataType::Int => write!(
+                &mut builder,
+                "integer check ({} between {} and {})", Quoted(&type_.name), i32::MIN, i32::MAX,
+            )?,
+            DataType::BigInt => builder.push_str("bigint"),
+            DataType::Bool => write!(&mut builder, "boolean check ({} in (0, 1))", Quoted(&type_.name))?,
+            // numeric affinity, values that would lose precision as a real are kept as text
+            DataType::Decimal => match type_.args.as_slice() {
+                [precision] => write!(&mut builder, "decimal({precision})")?,
+                [precision, scale] => write!(&mut builder, "decimal({precision}, {scale})")?,
+                _ => builder.push_str("decimal"),
+            },
+            DataType::Float => builder.push_str("real"),
+            DataType::Date => builder.push_str("date"),
+            DataType::Time => builder.push_str("time"),
+            // stored as microseconds
+            DataType::Duration => builder.push_str("interval"),
+            DataType::Json => builder.push_str("json"),
+            DataType::Bytes => match type_.args.as_slice() {
+                [max] => write!(&mut builder, "blob check (length({}) <= {max})", Quoted(&type_.name))?,
+                _ => builder.push_str("blob"),
+            },
+            // only types that define their own `column_type` use it
+            DataType::Enum => builder.push_str("text"),
+        };
+
+        Ok(builder)
+    }
+
+    fn data_type(sql_type: &str) -> Option<(DataType, Vec<u64>)> {
+        let sql_type = sql_type.to_ascii_lowercase();
+        let (name, args) = match sql_type.split_once('(') {
+            Some((name, args)) => {
+                let args = args.trim_end_matches(')')
+                    .split(',')
+                    .map(|arg| arg.trim().parse::<u64>().ok())
+                    .collect::<Option<Vec<_>>>()?;
+                (name.trim(), args)
+            }
+            None => (sql_type.trim(), Vec::new()),
+        };
+        let data_type = match name {
+            "char" if args == [36] => return Some((DataType::UUID, Vec::new())),
+            "uuid" => DataType::UUID,
+            "varchar" | "char" | "character" => DataType::String,
+            "text" => DataType::String,
+            "datetime" | "timestamp" => DataType::DateTime,
+            "int" | "integer" | "smallint" => DataType::Int,
+            "bigint" => DataType::BigInt,
+            "boolean" | "bool" => DataType::Bool,
+            "decimal" | "numeric" => DataType::Decimal,
+            "real" | "float" | "double" | "double precision" => DataType::Float,
+            "date" => DataType::Date,
+            "time" => DataType::Time,
+            "interval" => DataType::Duration,
+            "json" => DataType::Json,
+            "blob" => DataType::Bytes,
+            _ => return None,
+        };
+        Some((data_type, args))
+    }
+}
+
+/// Reads a value back into the type its column was declared with.
+fn decode(value: Value, data_type: DataType, args: &[u64]) -> anyhow::Result<Value> {
+    Ok(match (data_type, value) {
+        (_, Value::Null) => Value::Null,
+        (DataType::Bool, Value::Int(value)) => Value::Bool(value != 0),
+        (DataType::Decimal, value @ (Value::Float(_) | Value::Int(_) | Value::String(_))) => {
+            let Value::Decimal(value) = value.coerce(DataType::Decimal).map_err(|e| anyhow!(e))?.into_owned() else {
+                unreachable!()
+            };
+            match args {
+                [] => Value::Decimal(value.normalize()),
+                args => Value::Decimal(DecimalType::fit(value, args).map_err(|e| anyhow!(e))?),
+            }
+        }
+        (DataType::Duration, Value::Int(value)) => Value::Duration(TimeDelta::microseconds(value)),
+        (DataType::Json, Value::String(value)) => Value::Json(serde_json::from_str(&value)?),
+        (DataType::Json, Value::Int(value)) => Value::Json(value.into()),
+        (DataType::Json, Value::Float(value)) => Value::Json(value.into()),
+        (data_type, value) => value.coerce(data_type)
+            .map_err(|e| anyhow!("invalid {} value: {e}", data_type.name()))?
+            .into_owned(),
+    })
+}
+
+struct SqliteDialect;
+
+impl Dialect for SqliteDialect {
+    fn placeholder(&self, index: usize) -> String {
+        format!("?{index}")
+    }
+
+    fn generated_value(&self, field: &ModelField) -> Option<Value> {
+        match field.repr.data_type() {
+            DataType::UUID => Some(Value::Uuid(Uuid::new_v4())),
+            _ => None,
+        }
+    }
+
+    fn no_limit(&self) -> &'static str {
+        // an offset is only accepted after a limit, a negative one has no upper bound
+        " limit -1"
+    }
+}
+
+impl ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            Value::Null => ToSqlOutput::from(rusqlite::types::Null),
+            Value::Bool(value) => ToSqlOutput::from(*value),
+            Value::Int(value) => ToSqlOutput::from(*value),
+            Value::String(value) => ToSqlOutput::from(value.as_str()),
+            Value::Float(value) => ToSqlOutput::from(*value),
+            Value::Duration(value) => ToSqlOutput::from(value.num_microseconds().ok_or_else(|| {
+                rusqlite::Error::ToSqlConversionFailure("duration is out of range".into())
+            })?),
+            Value::Json(value) => ToSqlOutput::from(value.to_string()),
+            Value::Bytes(value) => ToSqlOutput::from(value.as_slice()),
+            Value::Uuid(_) | Value::DateTime(_) | Value::Decimal(_) | Value::Date(_) | Value::Time(_) => {
+                ToSqlOutput::from(self.to_string())
+            }
+        })
+    }
+}
+
+impl FromSql for Value {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(Value::Null),
+            ValueRef::Integer(value) => Ok(Value::Int(value)),
+            ValueRef::Text(_) => String::column_result(value).map(Value::String),
+            ValueRef::Real(value) => Ok(Value::Float(value)),
+            ValueRef::Blob(value) => Ok(Value::Bytes(value.to_vec())),
+        }
+    }
+}
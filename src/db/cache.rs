@@ -0,0 +1,134 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use async_trait::async_trait;
+use hashbrown::{HashMap, HashSet};
+use crate::db::ast::{Model, Query};
+use crate::db::backend::Transaction;
+use crate::db::parser::qql::Action;
+use crate::db::value::{QueryResult, Value};
+use crate::parser::Ident;
+
+/// Results of `@cache` queries by query and arguments, kept in process. Every insert, update or
+/// delete through the same context drops the results of the queries reading its model, writes
+/// in a transaction once it commits. Other processes writing to the database are not noticed
+/// until the results expire.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    queries: HashMap<String, CachedQuery>,
+    /// bumped by every write to a model, so results read before the write are not stored after it
+    generations: HashMap<String, u64>,
+}
+
+impl CacheState {
+    fn generation(&self, query: &Query) -> u64 {
+        QueryCache::models(query)
+            .map(|model| self.generations.get(model).copied().unwrap_or(0))
+            .sum()
+    }
+}
+
+#[derive(Debug)]
+struct CachedQuery {
+    models: Vec<String>,
+    /// by the `Debug` form of the arguments, which keeps their types apart
+    results: HashMap<String, (Instant, QueryResult)>,
+}
+
+impl QueryCache {
+    /// `None` when the query is not cached or its result for `args` expired.
+    pub fn get(&self, query: &Query, args: &[Value]) -> Option<QueryResult> {
+        query.cache?;
+        let mut state = self.state.lock().unwrap();
+        let results = &mut state.queries.get_mut(query.name.as_str())?.results;
+        let key = format!("{args:?}");
+        match results.get(&key) {
+            Some((expires, result)) if *expires > Instant::now() => Some(result.clone()),
+            Some(_) => {
+                results.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Taken before the query runs and handed to `insert`.
+    pub fn generation(&self, query: &Query) -> u64 {
+        self.state.lock().unwrap().generation(query)
+    }
+
+    /// Keeps the result unless the query is not cached or one of its models was written since
+    /// `generation` was taken.
+    pub fn insert(&self, query: &Query, args: &[Value], generation: u64, result: &QueryResult) {
+        let Some(duration) = query.cache else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if state.generation(query) != generation {
+            return;
+        }
+        let cached = state.queries.entry(query.name.to_string())
+            .or_insert_with(|| CachedQuery {
+                models: Self::models(query).map(str::to_owned).collect(),
+                results: HashMap::new(),
+            });
+        let now = Instant::now();
+        cached.results.retain(|_, (expires, _)| *expires > now);
+        cached.results.insert(format!("{args:?}"), (now + duration, result.clone()));
+    }
+
+    pub fn invalidate<'a>(&self, models: impl IntoIterator<Item = &'a str>) {
+        let mut state = self.state.lock().unwrap();
+        for model in models {
+            *state.generations.entry(model.to_owned()).or_default() += 1;
+            for cached in state.queries.values_mut() {
+                if cached.models.iter().any(|name| name == model) {
+                    cached.results.clear();
+                }
+            }
+        }
+    }
+
+    /// The models a query reads or writes.
+    pub(crate) fn models(query: &Query) -> impl Iterator<Item = &str> {
+        query.statement.selectors.iter().map(|selector| selector.name.as_str())
+    }
+}
+
+/// Collects the models its writes touch and invalidates them once the transaction commits, reads
+/// inside it bypass the cache.
+pub(crate) struct InvalidatingTransaction {
+    pub(crate) inner: Box<dyn Transaction>,
+    pub(crate) cache: Arc<QueryCache>,
+    pub(crate) written: HashSet<String>,
+}
+
+#[async_trait]
+impl Transaction for InvalidatingTransaction {
+    async fn execute(
+        &mut self,
+        models: &HashMap<Ident, Model>,
+        query: &Query,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult> {
+        if query.statement.action != Action::Select {
+            self.written.extend(QueryCache::models(query).map(str::to_owned));
+        }
+        self.inner.execute(models, query, args).await
+    }
+
+    async fn commit(self: Box<Self>) -> anyhow::Result<()> {
+        let InvalidatingTransaction { inner, cache, written } = *self;
+        let result = inner.commit().await;
+        cache.invalidate(written.iter().map(String::as_str));
+        result
+    }
+
+    async fn rollback(self: Box<Self>) -> anyhow::Result<()> {
+        self.inner.rollback().await
+    }
+}
@@ -0,0 +1,530 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use hashbrown::HashMap;
+use crate::db::Context;
+use crate::db::ast::{Model, ModelField, Query};
+use crate::db::backend::PostgresDialect;
+use crate::db::compile::{aggregate_type, compile, ParamSource};
+use crate::db::parser::qql::{Action, Aggregate, BinaryOp, Expr, Quantifier, Selector};
+use crate::db::types::{DataType, EnumType, Type};
+use crate::db::value::Value;
+use crate::parser::Ident;
+
+/// Writes a Rust module for services sharing the database: a struct per model, and a row struct
+/// and a function per query running the SQL aio compiles for Postgres through tokio-postgres.
+/// Queries the generated code cannot run the way aio does are skipped.
+pub fn codegen(context: &Context, out: &Path) -> anyhow::Result<()> {
+    let models = context.models();
+    let queries = context.queries();
+
+    let mut builder = String::new();
+    writeln!(&mut builder, "// Generated by `aio db codegen`, do not edit.")?;
+    writeln!(&mut builder, "// Queries run on any `tokio_postgres::GenericClient`, enums need the `derive` feature of")?;
+    writeln!(&mut builder, "// `postgres-types`. Field rules such as `@min` are only checked by aio.")?;
+
+    let mut sorted = models.values().collect::<Vec<_>>();
+    sorted.sort_by_key(|model| &model.name);
+
+    let mut enums = BTreeMap::<&str, &EnumType>::new();
+    for field in sorted.iter().flat_map(|model| &model.fields) {
+        if let Some(enum_) = field.repr.as_enum() {
+            enums.insert(&enum_.name, enum_);
+        }
+    }
+    for enum_ in enums.values() {
+        writeln!(&mut builder)?;
+        write_enum(&mut builder, enum_)?;
+    }
+    for model in sorted {
+        writeln!(&mut builder)?;
+        write_model(&mut builder, model)?;
+    }
+
+    let mut names = queries.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut root = Module::default();
+    for name in names {
+        match query_source(&models, &queries[name]) {
+            Ok(source) => {
+                let path = name.split('.').collect::<Vec<_>>();
+                root.insert(&path[..path.len() - 1], source);
+            }
+            Err(reason) => eprintln!("skipping query {name}: {reason}"),
+        }
+    }
+    root.write(&mut builder, 0)?;
+
+    std::fs::write(out, builder)?;
+    println!("wrote {}", out.display());
+    Ok(())
+}
+
+/// Queries nested by their namespace, `billing.invoice.Unpaid` is `billing::invoice::unpaid`.
+#[derive(Default)]
+struct Module {
+    items: Vec<String>,
+    modules: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn insert(&mut self, path: &[&str], item: String) {
+        match path {
+            [] => self.items.push(item),
+            [name, rest @ ..] => self.modules.entry(name.to_string()).or_default().insert(rest, item),
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) -> std::fmt::Result {
+        let indent = "    ".repeat(depth);
+        for item in &self.items {
+            writeln!(out)?;
+            for line in item.lines() {
+                match line {
+                    "" => writeln!(out)?,
+                    line => writeln!(out, "{indent}{line}")?,
+                }
+            }
+        }
+        for (name, module) in &self.modules {
+            writeln!(out)?;
+            writeln!(out, "{indent}pub mod {} {{", ident(name))?;
+            // for the enums
+            writeln!(out, "{indent}    #[allow(unused_imports)]")?;
+            writeln!(out, "{indent}    use super::*;")?;
+            module.write(out, depth + 1)?;
+            writeln!(out, "{indent}}}")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_enum(out: &mut String, enum_: &EnumType) -> std::fmt::Result {
+    writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, postgres_types::ToSql, postgres_types::FromSql)]")?;
+    writeln!(out, "#[postgres(name = {:?})]", enum_.name)?;
+    writeln!(out, "pub enum {} {{", ident(&enum_.name))?;
+    for variant in &enum_.variants {
+        writeln!(out, "    {},", ident(variant))?;
+    }
+    writeln!(out, "}}")
+}
+
+fn write_model(out: &mut String, model: &Model) -> std::fmt::Result {
+    writeln!(out, "#[derive(Debug, Clone)]")?;
+    writeln!(out, "pub struct {} {{", ident(&model.name))?;
+    for field in &model.fields {
+        // models are never read from rows, so fields tokio-postgres cannot read still get a type
+        let type_ = rust_type(field.repr.as_ref()).unwrap_or_else(|_| match field.repr.data_type() {
+            DataType::Duration => "chrono::TimeDelta".to_owned(),
+            _ => "String".to_owned(),
+        });
+        writeln!(out, "    pub {}: {},", ident(&field.name), optional(type_, field.optional))?;
+    }
+    writeln!(out, "}}")
+}
+
+/// The row struct and function of a query, or why the generated code cannot run it the way aio
+/// does.
+fn query_source(models: &HashMap<Ident, Model>, query: &Query) -> Result<String, String> {
+    let statement = &query.statement;
+    if statement.after.is_some() {
+        return Err("its SQL depends on whether AFTER is given a cursor".to_owned());
+    }
+    let list_argument = [&statement.where_clause.as_ref().map(|w| &w.expr), &statement.having.as_ref()]
+        .into_iter()
+        .flatten()
+        .any(|expr| uses_list_argument(expr));
+    if list_argument {
+        return Err("IN #argument binds a parameter per list element".to_owned());
+    }
+    for selector in &statement.selectors {
+        for field in selector.fields.iter().filter(|field| field.value.is_some()) {
+            let model_field = field_of(models, &selector.name, &field.name)?;
+            if model_field.repr.compared_in_rust() {
+                return Err(format!("{}.{} is encoded by aio", selector.name, field.name));
+            }
+        }
+    }
+
+    let arguments = argument_types(models, query)?;
+    let compiled = compile(&PostgresDialect, models, query, &vec![Value::Null; query.args.len()])
+        .map_err(|e| e.to_string())?;
+    if !compiled.verify.is_empty() {
+        return Err("it compares a field in rust, like a Hashed password".to_owned());
+    }
+
+    let mut params = Vec::new();
+    for (source, value) in compiled.sources.iter().zip(&compiled.params) {
+        let param = match source {
+            ParamSource::Argument(index) => ident(&query.args[*index]),
+            ParamSource::Literal | ParamSource::Variant(_) | ParamSource::Default(_) => {
+                literal(models, &statement.selectors, source, value)?
+            }
+            ParamSource::Now => "chrono::Utc::now().naive_utc()".to_owned(),
+            ParamSource::Generated => return Err("it binds a value generated by aio".to_owned()),
+        };
+        params.push(format!("&{param}"));
+    }
+
+    let name = query.name.rsplit('.').next().unwrap();
+    let function = ident(&snake_case(name));
+    let row = format!("{name}Row");
+    let mut signature = vec!["client: &impl tokio_postgres::GenericClient".to_owned()];
+    for (arg, type_) in query.args.iter().zip(&arguments) {
+        signature.push(format!("{}: {type_}", ident(arg)));
+    }
+    let signature = signature.join(", ");
+    let sql = format!("{:?}", compiled.sql);
+    let params = format!("&[{}]", params.join(", "));
+
+    let mut out = String::new();
+    if statement.action != Action::Select {
+        writeln!(out, "/// `{}`, returns the number of affected rows", query.name).unwrap();
+        writeln!(out, "pub async fn {function}({signature}) -> Result<u64, tokio_postgres::Error> {{").unwrap();
+        writeln!(out, "    client.execute(\n        {sql},\n        {params},\n    ).await").unwrap();
+        writeln!(out, "}}").unwrap();
+        return Ok(out);
+    }
+
+    let columns = row_columns(models, &statement.selectors)?;
+    writeln!(out, "#[derive(Debug, Clone)]").unwrap();
+    writeln!(out, "pub struct {row} {{").unwrap();
+    for (column, type_) in &columns {
+        writeln!(out, "    pub {}: {type_},", ident(column)).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+    writeln!(out, "impl {row} {{").unwrap();
+    writeln!(out, "    fn from_row(row: &tokio_postgres::Row) -> Result<Self, tokio_postgres::Error> {{").unwrap();
+    writeln!(out, "        Ok(Self {{").unwrap();
+    for (i, (column, _)) in columns.iter().enumerate() {
+        writeln!(out, "            {}: row.try_get({i})?,", ident(column)).unwrap();
+    }
+    writeln!(out, "        }})\n    }}\n}}\n").unwrap();
+
+    writeln!(out, "/// `{}`", query.name).unwrap();
+    let (returns, call, result, convert) = match statement.quantifier {
+        Quantifier::One => (format!("Option<{row}>"), "query_opt", "row", format!("row.as_ref().map({row}::from_row).transpose()")),
+        _ => (format!("Vec<{row}>"), "query", "rows", format!("rows.iter().map({row}::from_row).collect()")),
+    };
+    writeln!(out, "pub async fn {function}({signature}) -> Result<{returns}, tokio_postgres::Error> {{").unwrap();
+    writeln!(out, "    let {result} = client.{call}(\n        {sql},\n        {params},\n    ).await?;").unwrap();
+    writeln!(out, "    {convert}").unwrap();
+    writeln!(out, "}}").unwrap();
+    Ok(out)
+}
+
+/// The name and Rust type of every selected column, in the order `compile` selects them. Names
+/// several selectors share are prefixed with their model.
+fn row_columns(models: &HashMap<Ident, Model>, selectors: &[Selector]) -> Result<Vec<(String, String)>, String> {
+    let mut columns = Vec::new();
+    for selector in selectors {
+        let model = models.get(&selector.name)
+            .ok_or_else(|| format!("{} is not a model", selector.name))?;
+        let fields = if selector.fields.is_empty() {
+            model.fields.iter().map(|field| (field.name.to_string(), field, None)).collect::<Vec<_>>()
+        } else {
+            selector.fields.iter()
+                .map(|field| Ok((field.column(), field_of(models, &selector.name, &field.name)?, field.aggregate)))
+                .collect::<Result<Vec<_>, String>>()?
+        };
+
+        for (column, field, aggregate) in fields {
+            if field.repr.compared_in_rust() {
+                return Err(format!("{}.{} is decoded by aio", model.name, field.name));
+            }
+            let type_ = match aggregate {
+                Some(aggregate) => {
+                    let type_ = rust_type(aggregate_type(aggregate, field).as_ref())?;
+                    // only counts have a value without rows
+                    optional(type_, aggregate != Aggregate::Count)
+                }
+                None => optional(rust_type(field.repr.as_ref())?, field.optional),
+            };
+            columns.push((&model.name, column, type_));
+        }
+    }
+
+    let names = columns.iter().map(|(_, column, _)| column.clone()).collect::<Vec<_>>();
+    Ok(columns.into_iter()
+        .map(|(model, column, type_)| match names.iter().filter(|name| **name == column).count() {
+            1 => (column, type_),
+            _ => (format!("{}_{column}", snake_case(model)), type_),
+        })
+        .collect())
+}
+
+/// The parameter type of each argument, taken from the field it is written to or compared with.
+fn argument_types(models: &HashMap<Ident, Model>, query: &Query) -> Result<Vec<String>, String> {
+    let statement = &query.statement;
+    let mut inference = Inference {
+        models,
+        selectors: &statement.selectors,
+        query,
+        types: vec![None; query.args.len()],
+    };
+
+    if let Quantifier::Expr(expr) = &statement.quantifier {
+        inference.hint(expr, || Ok("i64".to_owned()));
+        inference.visit(expr);
+    }
+    if let Some(offset) = &statement.offset {
+        inference.hint(offset, || Ok("i64".to_owned()));
+        inference.visit(offset);
+    }
+    for selector in &statement.selectors {
+        for field in &selector.fields {
+            let Some(value) = &field.value else {
+                continue;
+            };
+            let model_field = field_of(models, &selector.name, &field.name)?;
+            inference.hint(value, || Ok(optional(param_type(model_field.repr.as_ref())?, model_field.optional)));
+            inference.visit(value);
+        }
+    }
+    if let Some(where_clause) = &statement.where_clause {
+        inference.visit(&where_clause.expr);
+    }
+    if let Some(having) = &statement.having {
+        inference.visit(having);
+    }
+
+    query.args.iter()
+        .zip(inference.types)
+        .map(|(arg, type_)| type_.unwrap_or_else(|| Err(format!("the type of argument {arg} cannot be inferred"))))
+        .collect()
+}
+
+struct Inference<'a> {
+    models: &'a HashMap<Ident, Model>,
+    selectors: &'a [Selector],
+    query: &'a Query,
+    /// the first type each argument was used as
+    types: Vec<Option<Result<String, String>>>,
+}
+
+impl<'a> Inference<'a> {
+    /// gives `expr` the type when it is an argument without one
+    fn hint(&mut self, expr: &Expr, type_: impl FnOnce() -> Result<String, String>) {
+        let Expr::Interp(arg) = expr else {
+            return;
+        };
+        let Some(index) = self.query.args.iter().position(|a| a == arg) else {
+            return;
+        };
+        if self.types[index].is_none() {
+            self.types[index] = Some(type_());
+        }
+    }
+
+    fn visit(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary(lhs, op, rhs) => {
+                let type_ = match op {
+                    BinaryOp::And | BinaryOp::Or => None,
+                    BinaryOp::Like => Some(Ok("&str".to_owned())),
+                    _ => self.operand_type(lhs).or_else(|| self.operand_type(rhs)),
+                };
+                if let Some(type_) = type_ {
+                    self.hint_type(lhs, type_.clone());
+                    self.hint_type(rhs, type_);
+                }
+                self.visit(lhs);
+                self.visit(rhs);
+            }
+            Expr::Between(inner, low, high) => {
+                if let Some(type_) = self.operand_type(inner) {
+                    self.hint_type(low, type_.clone());
+                    self.hint_type(high, type_);
+                }
+                self.visit(inner);
+                self.visit(low);
+                self.visit(high);
+            }
+            Expr::In(inner, values) => {
+                if let Some(type_) = self.operand_type(inner) {
+                    for value in values {
+                        self.hint_type(value, type_.clone());
+                    }
+                }
+                self.visit(inner);
+                for value in values {
+                    self.visit(value);
+                }
+            }
+            Expr::Unary(_, inner) | Expr::IsNull(inner, _) | Expr::Group(inner) | Expr::InArgument(inner, _) => {
+                self.visit(inner);
+            }
+            Expr::Number(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null | Expr::Interp(_)
+            | Expr::Field(..) | Expr::Variant(..) | Expr::Aggregate(..) => {}
+        }
+    }
+
+    fn hint_type(&mut self, expr: &Expr, type_: Result<String, String>) {
+        self.hint(expr, || type_);
+    }
+
+    /// the parameter type of a field, aggregate or arithmetic on them
+    fn operand_type(&self, expr: &Expr) -> Option<Result<String, String>> {
+        match expr {
+            Expr::Field(model, field) => {
+                let (_, field) = self.resolve(model.as_ref(), field)?;
+                Some(param_type(field.repr.as_ref()))
+            }
+            Expr::Aggregate(aggregate, model, field) => {
+                let (_, field) = self.resolve(model.as_ref(), field)?;
+                Some(param_type(aggregate_type(*aggregate, field).as_ref()))
+            }
+            Expr::Group(inner) | Expr::Unary(_, inner) => self.operand_type(inner),
+            Expr::Binary(lhs, op, rhs) if !matches!(op, BinaryOp::And | BinaryOp::Or) => {
+                self.operand_type(lhs).or_else(|| self.operand_type(rhs))
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve(&self, model: Option<&Ident>, field: &Ident) -> Option<(&'a Model, &'a ModelField)> {
+        self.selectors.iter()
+            .filter(|selector| model.is_none_or(|model| *model == selector.name))
+            .find_map(|selector| {
+                let model = self.models.get(&selector.name)?;
+                Some((model, model.get_field(field)?))
+            })
+    }
+}
+
+/// Rust literals for the values the compiler binds from the query itself.
+fn literal(
+    models: &HashMap<Ident, Model>,
+    selectors: &[Selector],
+    source: &ParamSource,
+    value: &Value,
+) -> Result<String, String> {
+    let field = match source {
+        ParamSource::Variant(name) => {
+            let Value::String(variant) = value else {
+                return Err(format!("{name} has a variant that is not a string"));
+            };
+            return Ok(format!("{}::{}", ident(name), ident(variant)));
+        }
+        ParamSource::Default(field) => match selectors {
+            [selector] => field_of(models, &selector.name, field)?,
+            _ => return Err(format!("the default of {field} has no model")),
+        },
+        _ => {
+            let Value::String(value) = value else {
+                return Err(format!("it binds {value}, which has no Rust literal"));
+            };
+            return Ok(format!("{value:?}"));
+        }
+    };
+
+    let data_type = field.repr.data_type();
+    Ok(match (value, field.repr.as_enum()) {
+        (Value::Null, _) => format!("None::<{}>", rust_type(field.repr.as_ref())?),
+        (Value::String(variant), Some(enum_)) => format!("{}::{}", ident(&enum_.name), ident(variant)),
+        (Value::String(value), None) => format!("{value:?}"),
+        (Value::Int(value), _) if data_type == DataType::Int => format!("{value}_i32"),
+        (Value::Int(value), _) => format!("{value}_i64"),
+        (Value::Bool(value), _) => value.to_string(),
+        (Value::Float(value), _) => format!("{value:?}_f64"),
+        (Value::Decimal(value), _) => format!(
+            "rust_decimal::Decimal::from_i128_with_scale({}, {})",
+            value.mantissa(), value.scale(),
+        ),
+        _ => return Err(format!("the default of {} has no Rust literal", field.name)),
+    })
+}
+
+fn field_of<'a>(models: &'a HashMap<Ident, Model>, model: &Ident, field: &Ident) -> Result<&'a ModelField, String> {
+    models.get(model)
+        .and_then(|m| m.get_field(field))
+        .ok_or_else(|| format!("{model} has no field {field}"))
+}
+
+fn uses_list_argument(expr: &Expr) -> bool {
+    match expr {
+        Expr::InArgument(..) => true,
+        Expr::Binary(lhs, _, rhs) => uses_list_argument(lhs) || uses_list_argument(rhs),
+        Expr::Unary(_, inner) | Expr::IsNull(inner, _) | Expr::Group(inner) => uses_list_argument(inner),
+        Expr::Between(inner, low, high) => [inner, low, high].iter().any(|expr| uses_list_argument(expr)),
+        Expr::In(inner, values) => uses_list_argument(inner) || values.iter().any(uses_list_argument),
+        _ => false,
+    }
+}
+
+/// The type tokio-postgres reads a column of `type_` as.
+fn rust_type(type_: &dyn Type) -> Result<String, String> {
+    if let Some(enum_) = type_.as_enum() {
+        return Ok(ident(&enum_.name));
+    }
+    let data_type = type_.data_type();
+    Ok(match data_type {
+        DataType::UUID => "uuid::Uuid",
+        DataType::String => "String",
+        DataType::DateTime => "chrono::NaiveDateTime",
+        DataType::Int => "i32",
+        DataType::BigInt => "i64",
+        DataType::Bool => "bool",
+        DataType::Decimal => "rust_decimal::Decimal",
+        DataType::Float => "f64",
+        DataType::Date => "chrono::NaiveDate",
+        DataType::Time => "chrono::NaiveTime",
+        DataType::Json => "serde_json::Value",
+        DataType::Bytes => "Vec<u8>",
+        DataType::Duration | DataType::Enum => {
+            return Err(format!("tokio-postgres has no type for {}", data_type.name()));
+        }
+    }.to_owned())
+}
+
+/// The type an argument of `type_` is passed as, borrowed where the column type is owned.
+fn param_type(type_: &dyn Type) -> Result<String, String> {
+    let type_ = rust_type(type_)?;
+    Ok(match type_.as_str() {
+        "String" => "&str".to_owned(),
+        "Vec<u8>" => "&[u8]".to_owned(),
+        "serde_json::Value" => "&serde_json::Value".to_owned(),
+        _ => type_,
+    })
+}
+
+fn optional(type_: String, optional: bool) -> String {
+    match optional {
+        true => format!("Option<{type_}>"),
+        false => type_,
+    }
+}
+
+/// `CurrentUser` as `current_user`
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if previous_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            previous_lower = false;
+        } else {
+            out.push(c);
+            previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+    }
+    out
+}
+
+/// Names that are Rust keywords are written as raw identifiers.
+fn ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+    match KEYWORDS.contains(&name) {
+        true => format!("r#{name}"),
+        false => name.to_owned(),
+    }
+}
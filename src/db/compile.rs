@@ -0,0 +1,799 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::sync::Arc;
+use anyhow::{anyhow, bail};
+use chrono::Utc;
+use hashbrown::HashMap;
+use crate::db::ast::{Model, ModelField, Query, Timestamp};
+use crate::db::parser::qql::{Action, Aggregate, BinaryOp, Expr, Quantifier, Selector, UnaryOp};
+use crate::db::quote::{Qualified, Quoted};
+use crate::db::types::{BigIntType, DataType, DecimalType, FloatType, Type};
+use crate::db::value::{QueryResult, Value};
+use crate::parser::Ident;
+
+/// The parts of SQL generation that differ between drivers
+pub trait Dialect {
+    /// placeholder for the `index`th bound parameter, starting at 1
+    fn placeholder(&self, index: usize) -> String;
+
+    /// a value computed in rust for a field that an insert does not set
+    fn generated_value(&self, field: &ModelField) -> Option<Value>;
+
+    /// written before an offset when a select has no limit
+    fn no_limit(&self) -> &'static str {
+        ""
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledQuery {
+    pub sql: String,
+    pub params: Vec<Value>,
+    /// where each of `params` comes from
+    pub(crate) sources: Vec<ParamSource>,
+    pub returns_rows: bool,
+    /// types of the selected columns, to decode what they store
+    pub(crate) columns: Vec<Arc<dyn Type>>,
+    /// comparisons the database cannot do, each selects one extra column after `columns`
+    pub(crate) verify: Vec<Verify>,
+    /// applied after `verify`, since the database cannot limit rows it does not filter
+    pub(crate) limit: Option<usize>,
+    /// rows skipped after `verify`, for the same reason
+    pub(crate) offset: usize,
+}
+
+/// Where a bound parameter comes from, so code generated from a query can bind the same
+/// parameters without running the compiler.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ParamSource {
+    /// the query argument at the index
+    Argument(usize),
+    /// a string written in the query
+    Literal,
+    /// a variant of the named enum
+    Variant(Ident),
+    /// the default of the field an insert does not set
+    Default(Ident),
+    /// the current time, for timestamps
+    Now,
+    /// computed by the dialect, see `Dialect::generated_value`
+    Generated,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Verify {
+    type_: Arc<dyn Type>,
+    value: Value,
+}
+
+impl CompiledQuery {
+    /// Filters rows by the comparisons done in rust, then decodes every column.
+    pub fn finish(&self, mut result: QueryResult) -> anyhow::Result<QueryResult> {
+        if !self.returns_rows {
+            return Ok(result);
+        }
+
+        let mut filter = self.row_filter();
+        let mut rows = Vec::with_capacity(result.rows.len());
+        for row in result.rows {
+            if filter.done() {
+                break;
+            }
+            if let Some(row) = filter.accept(row)? {
+                rows.push(row);
+            }
+        }
+
+        result.columns.truncate(self.columns.len());
+        result.affected = rows.len() as u64;
+        result.rows = rows;
+        Ok(result)
+    }
+
+    /// `finish` for one row at a time, for drivers that stream rows.
+    pub fn row_filter(&self) -> RowFilter<'_> {
+        RowFilter {
+            query: self,
+            skip: self.offset,
+            taken: 0,
+        }
+    }
+}
+
+pub struct RowFilter<'a> {
+    query: &'a CompiledQuery,
+    skip: usize,
+    taken: usize,
+}
+
+impl RowFilter<'_> {
+    /// The limit has been reached, no further row is accepted.
+    pub fn done(&self) -> bool {
+        self.query.limit.is_some_and(|limit| self.taken >= limit)
+    }
+
+    /// The decoded row, `None` when it fails a comparison done in rust or is skipped by the offset.
+    pub fn accept(&mut self, mut row: Vec<Value>) -> anyhow::Result<Option<Vec<Value>>> {
+        let visible = self.query.columns.len();
+        for (verify, stored) in self.query.verify.iter().zip(&row[visible..]) {
+            if !verify.type_.verify(stored, &verify.value).map_err(|e| anyhow!(e))? {
+                return Ok(None);
+            }
+        }
+        if self.skip > 0 {
+            self.skip -= 1;
+            return Ok(None);
+        }
+
+        row.truncate(visible);
+        let row = row.into_iter()
+            .zip(&self.query.columns)
+            .map(|(value, type_)| type_.decode(value).map_err(|e| anyhow!(e)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.taken += 1;
+        Ok(Some(row))
+    }
+}
+
+pub fn compile(
+    dialect: &dyn Dialect,
+    models: &HashMap<Ident, Model>,
+    query: &Query,
+    args: &[Value],
+) -> anyhow::Result<CompiledQuery> {
+    if args.len() != query.args.len() {
+        bail!("query {} expects {} arguments, found {}", query.name, query.args.len(), args.len());
+    }
+
+    let mut compiler = Compiler {
+        dialect,
+        models,
+        query,
+        args,
+        bound: vec![None; args.len()],
+        params: Vec::new(),
+        sources: Vec::new(),
+        sql: String::new(),
+        columns: Vec::new(),
+        verify: Vec::new(),
+        limit: None,
+        offset: 0,
+    };
+    compiler.compile()?;
+
+    Ok(CompiledQuery {
+        sql: compiler.sql,
+        params: compiler.params,
+        sources: compiler.sources,
+        returns_rows: query.statement.action == Action::Select,
+        columns: compiler.columns,
+        verify: compiler.verify.into_iter().map(|verified| verified.verify).collect(),
+        limit: compiler.limit,
+        offset: compiler.offset,
+    })
+}
+
+/// The order fields of the last row a page ended on, read from the argument named by `AFTER`.
+/// One order field takes a plain value, several take a JSON array with a value per field.
+/// `None` when the statement has no cursor or the argument is null, which starts at the first page.
+pub(crate) fn cursor(models: &HashMap<Ident, Model>, query: &Query, args: &[Value]) -> anyhow::Result<Option<Vec<Value>>> {
+    let statement = &query.statement;
+    let Some(after) = &statement.after else {
+        return Ok(None);
+    };
+    let index = query.args.iter()
+        .position(|a| a == after)
+        .ok_or_else(|| anyhow!("query {} has no argument {:?}", query.name, after))?;
+
+    let values = match (&args[index], statement.order_by.as_slice()) {
+        (Value::Null, _) => return Ok(None),
+        (value, [_]) => vec![value.clone()],
+        (value, _) => list_argument(value, None)
+            .map_err(|e| anyhow!("the cursor of query {} {e}", query.name))?,
+    };
+    if values.len() != statement.order_by.len() {
+        bail!("the cursor of query {} needs {} values, found {}", query.name, statement.order_by.len(), values.len());
+    }
+
+    let mut cursor = Vec::with_capacity(values.len());
+    for (value, order) in values.into_iter().zip(&statement.order_by) {
+        let model = match (&order.model, statement.selectors.as_slice()) {
+            (Some(model), _) => model,
+            (None, [selector]) => &selector.name,
+            (None, _) => bail!("query {} orders by an ambiguous field {}", query.name, order.field),
+        };
+        let field = models.get(model)
+            .and_then(|model| model.get_field(&order.field))
+            .ok_or_else(|| anyhow!("{} has no field {}", model, order.field))?;
+        if value.is_null() {
+            bail!("the cursor of query {} has no value for {}", query.name, order.field);
+        }
+        let value = value.coerce(field.repr.data_type())
+            .map_err(|e| anyhow!("invalid cursor value for {}.{}: {e}", model, field.name))?;
+        cursor.push(value.into_owned());
+    }
+    Ok(Some(cursor))
+}
+
+/// The type of an aggregate's column, sums of integers widen so they do not overflow.
+pub(crate) fn aggregate_type(aggregate: Aggregate, field: &ModelField) -> Arc<dyn Type> {
+    match (aggregate, field.repr.data_type()) {
+        (Aggregate::Count, _) | (Aggregate::Sum, DataType::Int) => Arc::new(BigIntType),
+        (Aggregate::Sum | Aggregate::Avg, DataType::Float) => Arc::new(FloatType),
+        (Aggregate::Sum | Aggregate::Avg, _) => Arc::new(DecimalType),
+        (Aggregate::Min | Aggregate::Max, _) => field.repr.clone(),
+    }
+}
+
+/// The elements of a list argument, such as the one of `IN #ids`, which arrives as a JSON array
+/// or a string holding one. They are converted to `data_type` when it is given.
+pub(crate) fn list_argument(value: &Value, data_type: Option<DataType>) -> Result<Vec<Value>, String> {
+    let values: Vec<_> = match value {
+        Value::Json(serde_json::Value::Array(values)) => values.iter().map(json_value).collect(),
+        Value::String(string) => match serde_json::from_str(string) {
+            Ok(serde_json::Value::Array(values)) => values.iter().map(json_value).collect(),
+            _ => return Err(format!("must be a JSON array, found {value}")),
+        },
+        value => return Err(format!("must be a JSON array, found {value}")),
+    };
+    match data_type {
+        Some(data_type) => values.into_iter()
+            .map(|value| value.coerce(data_type)
+                .map(Cow::into_owned)
+                .map_err(|e| format!("has an invalid value: {e}")))
+            .collect(),
+        None => Ok(values),
+    }
+}
+
+pub(crate) fn json_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(value) => Value::Bool(*value),
+        serde_json::Value::Number(value) => match value.as_i64() {
+            Some(value) => Value::Int(value),
+            None => Value::Float(value.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(value) => Value::String(value.clone()),
+        value => Value::Json(value.clone()),
+    }
+}
+
+struct Verified {
+    model: Ident,
+    field: Ident,
+    verify: Verify,
+}
+
+struct Compiler<'a> {
+    dialect: &'a dyn Dialect,
+    models: &'a HashMap<Ident, Model>,
+    query: &'a Query,
+    args: &'a [Value],
+    /// placeholder index of each argument, once it has been bound
+    bound: Vec<Option<usize>>,
+    params: Vec<Value>,
+    sources: Vec<ParamSource>,
+    sql: String,
+    columns: Vec<Arc<dyn Type>>,
+    /// comparisons moved out of the where clause
+    verify: Vec<Verified>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self) -> anyhow::Result<()> {
+        let statement = &self.query.statement;
+        if let Some(where_clause) = &statement.where_clause {
+            self.collect_verify(&where_clause.expr)?;
+        }
+
+        match statement.action {
+            Action::Select => self.compile_select()?,
+            Action::Insert => self.compile_insert()?,
+            Action::Update => self.compile_update()?,
+            Action::Delete => {
+                let selector = self.single_selector()?;
+                write!(&mut self.sql, "delete from {}", Quoted(&selector.name))?;
+            }
+        }
+
+        let cursor = cursor(self.models, self.query, self.args)?;
+        match (&statement.where_clause, cursor) {
+            (Some(where_clause), Some(cursor)) => {
+                self.sql.push_str(" where (");
+                self.compile_expr(&where_clause.expr)?;
+                self.sql.push_str(") and ");
+                self.compile_keyset(cursor)?;
+            }
+            (Some(where_clause), None) => {
+                self.sql.push_str(" where ");
+                self.compile_expr(&where_clause.expr)?;
+            }
+            (None, Some(cursor)) => {
+                self.sql.push_str(" where ");
+                self.compile_keyset(cursor)?;
+            }
+            (None, None) => {}
+        }
+
+        for (i, group) in statement.group_by.iter().enumerate() {
+            self.sql.push_str(if i == 0 { " group by " } else { ", " });
+            self.compile_field(group.model.as_ref(), &group.field)?;
+        }
+        if let Some(having) = &statement.having {
+            self.sql.push_str(" having ");
+            self.compile_expr(having)?;
+        }
+
+        for (i, order) in statement.order_by.iter().enumerate() {
+            self.sql.push_str(if i == 0 { " order by " } else { ", " });
+            self.compile_field(order.model.as_ref(), &order.field)?;
+            if order.descending {
+                self.sql.push_str(" desc");
+            }
+        }
+
+        if statement.action == Action::Select && !self.verify.is_empty() {
+            self.limit = match &statement.quantifier {
+                Quantifier::One => Some(1),
+                Quantifier::All => None,
+                Quantifier::Number(n) => Some(usize::try_from(*n)?),
+                Quantifier::Expr(Expr::Interp(arg)) => match &self.args[self.arg_index(arg)?] {
+                    Value::Int(n) => Some(usize::try_from(*n)?),
+                    value => bail!("query {} has a non-integer quantifier: {value}", self.query.name),
+                },
+                Quantifier::Expr(_) => bail!("query {} compares in rust, its quantifier must be a number or an argument", self.query.name),
+            };
+            self.offset = match &statement.offset {
+                None => 0,
+                Some(Expr::Number(n)) => usize::try_from(*n)?,
+                Some(Expr::Interp(arg)) => match &self.args[self.arg_index(arg)?] {
+                    Value::Int(n) => usize::try_from(*n)?,
+                    value => bail!("query {} has a non-integer offset: {value}", self.query.name),
+                },
+                Some(_) => bail!("query {} compares in rust, its offset must be a number or an argument", self.query.name),
+            };
+        } else if statement.action == Action::Select {
+            match &statement.quantifier {
+                Quantifier::One => self.sql.push_str(" limit 1"),
+                Quantifier::All => {}
+                Quantifier::Number(n) => write!(&mut self.sql, " limit {n}")?,
+                Quantifier::Expr(expr) => {
+                    self.sql.push_str(" limit ");
+                    self.compile_expr(expr)?;
+                }
+            }
+            if let Some(offset) = &statement.offset {
+                if matches!(statement.quantifier, Quantifier::All) {
+                    self.sql.push_str(self.dialect.no_limit());
+                }
+                self.sql.push_str(" offset ");
+                self.compile_expr(offset)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// rows after `cursor` in the order of the statement, `(a > $1 or (a = $1 and b < $2))` for
+    /// `ORDER BY a, b DESC`
+    fn compile_keyset(&mut self, cursor: Vec<Value>) -> anyhow::Result<()> {
+        let order_by = &self.query.statement.order_by;
+        // there is only a cursor when the statement names its argument
+        let after = self.arg_index(self.query.statement.after.as_ref().unwrap())?;
+        let placeholders = cursor.into_iter()
+            .map(|value| {
+                self.params.push(value);
+                self.sources.push(ParamSource::Argument(after));
+                self.dialect.placeholder(self.params.len())
+            })
+            .collect::<Vec<_>>();
+
+        // a row comes after the cursor when it passes it on the first order field that differs
+        self.sql.push('(');
+        for (i, order) in order_by.iter().enumerate() {
+            if i > 0 {
+                self.sql.push_str(" or (");
+            }
+            for (equal, placeholder) in order_by[..i].iter().zip(&placeholders) {
+                self.compile_field(equal.model.as_ref(), &equal.field)?;
+                write!(&mut self.sql, " = {placeholder} and ")?;
+            }
+            self.compile_field(order.model.as_ref(), &order.field)?;
+            let op = if order.descending { "<" } else { ">" };
+            write!(&mut self.sql, " {op} {}", placeholders[i])?;
+            if i > 0 {
+                self.sql.push(')');
+            }
+        }
+        self.sql.push(')');
+        Ok(())
+    }
+
+    fn compile_field(&mut self, model: Option<&Ident>, field: &Ident) -> anyhow::Result<()> {
+        match model {
+            Some(model) => write!(&mut self.sql, "{}", Qualified(model, field))?,
+            None => write!(&mut self.sql, "{}", Quoted(field))?,
+        }
+        Ok(())
+    }
+
+    fn compile_select(&mut self) -> anyhow::Result<()> {
+        let selectors = &self.query.statement.selectors;
+        let qualify = selectors.len() > 1;
+
+        self.sql.push_str("select ");
+        let mut first = true;
+        for selector in selectors {
+            let model = self.model(&selector.name)?;
+            let fields = if selector.fields.is_empty() {
+                model.fields.iter().map(|f| (&f.name, None)).collect::<Vec<_>>()
+            } else {
+                selector.fields.iter().map(|f| (&f.name, f.aggregate)).collect::<Vec<_>>()
+            };
+
+            for (field, aggregate) in fields {
+                if !first {
+                    self.sql.push_str(", ");
+                }
+                first = false;
+
+                let model_field = self.field(model, field)?;
+                let qualifier = qualify.then_some(&selector.name);
+                match aggregate {
+                    Some(aggregate) => {
+                        write!(&mut self.sql, "{}(", aggregate.function())?;
+                        self.compile_field(qualifier, field)?;
+                        write!(&mut self.sql, ") as {}", Quoted(&aggregate.column(field)))?;
+                        self.columns.push(aggregate_type(aggregate, model_field));
+                    }
+                    None => {
+                        self.compile_field(qualifier, field)?;
+                        self.columns.push(model_field.repr.clone());
+                    }
+                }
+            }
+        }
+        for verified in &self.verify {
+            if qualify {
+                write!(&mut self.sql, ", {}", Qualified(&verified.model, &verified.field))?;
+            } else {
+                write!(&mut self.sql, ", {}", Quoted(&verified.field))?;
+            }
+        }
+
+        self.sql.push_str(" from ");
+        for (i, selector) in selectors.iter().enumerate() {
+            if i > 0 {
+                self.sql.push_str(", ");
+            }
+            write!(&mut self.sql, "{}", Quoted(&selector.name))?;
+        }
+
+        Ok(())
+    }
+
+    fn compile_insert(&mut self) -> anyhow::Result<()> {
+        let selector = self.single_selector()?;
+        let model = self.model(&selector.name)?;
+
+        let mut columns = Vec::new();
+        let mut values = Vec::new();
+        for field in &selector.fields {
+            let value = field.value.as_ref()
+                .ok_or_else(|| anyhow!("{}.{} has no value", selector.name, field.name))?;
+            columns.push(field.name.as_str());
+            values.push(Some((&field.name, value)));
+        }
+        let mut generated = Vec::new();
+        for field in &model.fields {
+            if selector.fields.iter().any(|f| f.name == field.name) {
+                continue;
+            }
+            if let Some(value) = field.default_value() {
+                let value = field.repr.encode(value)
+                    .map_err(|e| anyhow!("unable to store {}.{}: {e}", model.name, field.name))?;
+                let source = match field.timestamp {
+                    Some(_) => ParamSource::Now,
+                    None => ParamSource::Default(field.name.clone()),
+                };
+                columns.push(field.name.as_str());
+                values.push(None);
+                generated.push((value, source));
+            } else if let Some(value) = self.dialect.generated_value(field) {
+                columns.push(field.name.as_str());
+                values.push(None);
+                generated.push((value, ParamSource::Generated));
+            }
+        }
+
+        write!(&mut self.sql, "insert into {} (", Quoted(&selector.name))?;
+        for (i, column) in columns.iter().enumerate() {
+            if i > 0 {
+                self.sql.push_str(", ");
+            }
+            write!(&mut self.sql, "{}", Quoted(column))?;
+        }
+        self.sql.push_str(") values (");
+
+        let mut generated = generated.into_iter();
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                self.sql.push_str(", ");
+            }
+            match value {
+                Some((field, expr)) => self.compile_field_value(model, field, expr)?,
+                None => {
+                    let (value, source) = generated.next().unwrap();
+                    self.bind(value, source);
+                }
+            }
+        }
+        self.sql.push(')');
+
+        Ok(())
+    }
+
+    fn compile_update(&mut self) -> anyhow::Result<()> {
+        let selector = self.single_selector()?;
+        let model = self.model(&selector.name)?;
+
+        write!(&mut self.sql, "update {} set ", Quoted(&selector.name))?;
+        let mut assigned = 0;
+        for field in &selector.fields {
+            if assigned > 0 {
+                self.sql.push_str(", ");
+            }
+            assigned += 1;
+            let value = field.value.as_ref()
+                .ok_or_else(|| anyhow!("{}.{} has no value", selector.name, field.name))?;
+            write!(&mut self.sql, "{} = ", Quoted(&field.name))?;
+            self.compile_field_value(model, &field.name, value)?;
+        }
+        for field in &model.fields {
+            if field.timestamp != Some(Timestamp::UpdatedAt) || selector.fields.iter().any(|f| f.name == field.name) {
+                continue;
+            }
+            if assigned > 0 {
+                self.sql.push_str(", ");
+            }
+            assigned += 1;
+            write!(&mut self.sql, "{} = ", Quoted(&field.name))?;
+            self.bind(Value::DateTime(Utc::now().naive_utc()), ParamSource::Now);
+        }
+        if assigned == 0 {
+            bail!("{} sets no fields", selector.name);
+        }
+
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> anyhow::Result<()> {
+        match expr {
+            // checked by `CompiledQuery::finish`
+            Expr::Binary(lhs, BinaryOp::Eq, rhs) if self.verified(lhs, rhs)?.is_some() => {
+                self.sql.push_str("true");
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                self.compile_expr(lhs)?;
+                let op = match op {
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Rem => "%",
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Lt => "<",
+                    BinaryOp::Le => "<=",
+                    BinaryOp::Gt => ">",
+                    BinaryOp::Ge => ">=",
+                    BinaryOp::Eq => "=",
+                    BinaryOp::Ne => "<>",
+                    BinaryOp::Like => "like",
+                    BinaryOp::And => "and",
+                    BinaryOp::Or => "or",
+                };
+                write!(&mut self.sql, " {op} ")?;
+                self.compile_expr(rhs)?;
+                if op == "like" {
+                    // neither database agrees on an escape character otherwise
+                    self.sql.push_str(" escape '\\'");
+                }
+            }
+            Expr::Unary(op, rhs) => {
+                match op {
+                    UnaryOp::Not => self.sql.push_str("not "),
+                    UnaryOp::Negative => self.sql.push('-'),
+                }
+                self.compile_expr(rhs)?;
+            }
+            Expr::Number(n) => write!(&mut self.sql, "{n}")?,
+            Expr::String(value) => {
+                self.bind(Value::String(value.clone()), ParamSource::Literal);
+            }
+            Expr::Bool(value) => write!(&mut self.sql, "{value}")?,
+            Expr::Null => self.sql.push_str("null"),
+            Expr::In(lhs, values) => {
+                self.compile_expr(lhs)?;
+                self.sql.push_str(" in (");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        self.sql.push_str(", ");
+                    }
+                    self.compile_expr(value)?;
+                }
+                self.sql.push(')');
+            }
+            Expr::InArgument(lhs, arg) => {
+                let data_type = match lhs.as_ref() {
+                    Expr::Field(model, field) => {
+                        let model = match model {
+                            Some(model) => model,
+                            None => &self.single_selector()?.name,
+                        };
+                        Some(self.field(self.model(model)?, field)?.repr.data_type())
+                    }
+                    _ => None,
+                };
+                let index = self.arg_index(arg)?;
+                let value = &self.args[index];
+                let values = list_argument(value, data_type)
+                    .map_err(|e| anyhow!("argument {arg} of query {} {e}", self.query.name))?;
+                // `in ()` is not valid sql, and nothing is in an empty list
+                if values.is_empty() {
+                    self.sql.push_str("false");
+                    return Ok(());
+                }
+
+                self.compile_expr(lhs)?;
+                self.sql.push_str(" in (");
+                for (i, value) in values.into_iter().enumerate() {
+                    if i > 0 {
+                        self.sql.push_str(", ");
+                    }
+                    self.bind(value, ParamSource::Argument(index));
+                }
+                self.sql.push(')');
+            }
+            Expr::IsNull(inner, negated) => {
+                self.compile_expr(inner)?;
+                self.sql.push_str(if *negated { " is not null" } else { " is null" });
+            }
+            Expr::Between(inner, low, high) => {
+                self.compile_expr(inner)?;
+                self.sql.push_str(" between ");
+                self.compile_expr(low)?;
+                self.sql.push_str(" and ");
+                self.compile_expr(high)?;
+            }
+            Expr::Interp(arg) => {
+                let index = self.arg_index(arg)?;
+                let placeholder = match self.bound[index] {
+                    Some(placeholder) => placeholder,
+                    None => {
+                        let placeholder = self.bind(self.args[index].clone(), ParamSource::Argument(index));
+                        self.bound[index] = Some(placeholder);
+                        return Ok(());
+                    }
+                };
+                self.sql.push_str(&self.dialect.placeholder(placeholder));
+            }
+            Expr::Field(model, field) => self.compile_field(model.as_ref(), field)?,
+            Expr::Aggregate(aggregate, model, field) => {
+                write!(&mut self.sql, "{}(", aggregate.function())?;
+                self.compile_field(model.as_ref(), field)?;
+                self.sql.push(')');
+            }
+            Expr::Variant(name, variant) => {
+                self.bind(Value::String(variant.to_string()), ParamSource::Variant(name.clone()));
+            }
+            Expr::Group(inner) => {
+                self.sql.push('(');
+                self.compile_expr(inner)?;
+                self.sql.push(')');
+            }
+        }
+        Ok(())
+    }
+
+    /// arguments written to a field are converted to its type first, so every driver stores the
+    /// same representation and constraints the database cannot check (decimal scale) still apply
+    fn compile_field_value(&mut self, model: &Model, field: &Ident, expr: &Expr) -> anyhow::Result<()> {
+        let Expr::Interp(arg) = expr else {
+            return self.compile_expr(expr);
+        };
+        let field = model.get_field(field)
+            .ok_or_else(|| anyhow!("{} has no field {}", model.name, field))?;
+        let index = self.arg_index(arg)?;
+
+        let value = self.args[index].coerce(field.repr.data_type())
+            .map_err(|e| anyhow!("invalid value for {}.{}: {e}", model.name, field.name))?;
+        let value = match value.as_ref() {
+            Value::Decimal(value) => DecimalType::fit(*value, &field.args)
+                .map(Value::Decimal)
+                .map_err(|e| anyhow!("{e} in {}.{}", model.name, field.name))?,
+            _ => value.into_owned(),
+        };
+        field.repr.validate(&value)
+            .map_err(|e| anyhow!("invalid value for {}.{}: {e}", model.name, field.name))?;
+        let value = field.repr.encode(value)
+            .map_err(|e| anyhow!("unable to store {}.{}: {e}", model.name, field.name))?;
+        self.bind(value, ParamSource::Argument(index));
+        Ok(())
+    }
+
+    /// records the `field == #argument` comparisons at the top level of the where clause that
+    /// are done in rust, validation rejects them anywhere else
+    fn collect_verify(&mut self, expr: &Expr) -> anyhow::Result<()> {
+        match expr {
+            Expr::Binary(lhs, BinaryOp::And, rhs) => {
+                self.collect_verify(lhs)?;
+                self.collect_verify(rhs)
+            }
+            Expr::Group(inner) => self.collect_verify(inner),
+            Expr::Binary(lhs, BinaryOp::Eq, rhs) => {
+                if let Some(verified) = self.verified(lhs, rhs)? {
+                    self.verify.push(verified);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `Some` when the comparison is between a field whose type is `compared_in_rust` and an argument
+    fn verified(&self, lhs: &Expr, rhs: &Expr) -> anyhow::Result<Option<Verified>> {
+        let ((Expr::Field(model, field), Expr::Interp(arg)) | (Expr::Interp(arg), Expr::Field(model, field))) = (lhs, rhs) else {
+            return Ok(None);
+        };
+        let model = match model {
+            Some(model) => model,
+            None => &self.single_selector()?.name,
+        };
+        let type_ = &self.field(self.model(model)?, field)?.repr;
+        if !type_.compared_in_rust() {
+            return Ok(None);
+        }
+        Ok(Some(Verified {
+            model: model.clone(),
+            field: field.clone(),
+            verify: Verify {
+                type_: type_.clone(),
+                value: self.args[self.arg_index(arg)?].clone(),
+            },
+        }))
+    }
+
+    fn field(&self, model: &'a Model, field: &Ident) -> anyhow::Result<&'a ModelField> {
+        model.get_field(field)
+            .ok_or_else(|| anyhow!("{} has no field {}", model.name, field))
+    }
+
+    fn arg_index(&self, arg: &Ident) -> anyhow::Result<usize> {
+        self.query.args.iter()
+            .position(|a| a == arg)
+            .ok_or_else(|| anyhow!("query {} has no argument {:?}", self.query.name, arg))
+    }
+
+    /// pushes a parameter and its placeholder, returning the placeholder index
+    fn bind(&mut self, value: Value, source: ParamSource) -> usize {
+        self.params.push(value);
+        self.sources.push(source);
+        let index = self.params.len();
+        self.sql.push_str(&self.dialect.placeholder(index));
+        index
+    }
+
+    fn single_selector(&self) -> anyhow::Result<&'a Selector> {
+        match self.query.statement.selectors.as_slice() {
+            [selector] => Ok(selector),
+            _ => bail!("query {} must use exactly one model", self.query.name),
+        }
+    }
+
+    fn model(&self, name: &Ident) -> anyhow::Result<&'a Model> {
+        self.models.get(name)
+            .ok_or_else(|| anyhow!("query {} uses unknown model {:?}", self.query.name, name))
+    }
+}
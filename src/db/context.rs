@@ -1,17 +1,33 @@
-use std::cell::RefCell;
-use std::path::PathBuf;
-use hashbrown::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use anyhow::anyhow;
+use hashbrown::{HashMap, HashSet};
 use crate::config;
 use crate::config::error::FromConfigError;
-use crate::db::backend::{Driver, PostgresDriver};
-use crate::db::ast::Model;
-use crate::db::types::TypeStore;
+use crate::db::backend::{ConnectOptions, Driver, MemoryDriver, Transaction, PoolOptions, PostgresDriver, SqliteDriver, SslMode};
+use crate::db::ast::{Model, Query};
+use crate::db::cache::{InvalidatingTransaction, QueryCache};
+use crate::db::lint::LintLevels;
+use crate::db::log::{LogMode, QueryLog};
+use crate::db::parser::qql::Action;
+use crate::db::rules;
+use crate::db::types::{EncryptedType, TypeStore};
+use crate::db::value::{QueryResult, Value};
 use crate::parser::Ident;
 
+/// Models and queries are only written while validating, readers take a cheap snapshot so no
+/// lock is held while a query runs.
 pub struct Context {
     pub(crate) type_store: TypeStore,
-    pub(crate) models: RefCell<HashMap<Ident, Model>>,
-    pub(crate) driver: RefCell<Box<dyn Driver>>,
+    pub(crate) models: RwLock<Arc<HashMap<Ident, Model>>>,
+    pub(crate) queries: RwLock<Arc<HashMap<Ident, Query>>>,
+    pub(crate) driver: Box<dyn Driver>,
+    /// shared with the driver, which records every statement it runs
+    pub(crate) log: Arc<QueryLog>,
+    /// results of `@cache` queries, shared with the transactions it begins
+    pub(crate) cache: Arc<QueryCache>,
+    pub(crate) lints: LintLevels,
     pub(crate) path: PathBuf,
 }
 
@@ -24,26 +40,202 @@ impl Context {
         }
         let path = path.canonicalize()?;
 
+        let mode = match db_config.get_optional("log_queries", |s, k| s.get_string(k))? {
+            Some(name) => LogMode::from_name(&name).ok_or_else(|| FromConfigError::Custom(format!(
+                "invalid database.log_queries: {name:?}, expected \"off\", \"on\" or \"values\"",
+            )))?,
+            None => LogMode::Off,
+        };
+        let slow = db_config.get_optional("slow_query_ms", |s, k| s.get_int::<u64>(k))?;
+        let log = Arc::new(QueryLog::new(mode, slow.map(Duration::from_millis)));
+
         let database_type = db_config.get_string("type")?;
         let driver: Box<dyn Driver> = match database_type.as_str() {
             "postgres" => {
-                let host = db_config.get_string("host")?;
-                let username = db_config.get_string("username")?;
-                let password = db_config.get_string("password")?;
-                let port = db_config.get_int::<u16>("port")?;
-                let database = db_config.get_string("database")?;
-                let driver = PostgresDriver::new(host, username, password, port, database)
+                let connect = Self::connect_options(db_config, &config.root)?;
+                let pool = PoolOptions {
+                    size: db_config.get_optional("pool_size", |s, k| s.get_int::<usize>(k))?,
+                    connect_timeout: db_config.get_optional("connect_timeout", |s, k| s.get_int::<u64>(k))?
+                        .map(Duration::from_secs),
+                };
+
+                let driver = PostgresDriver::new(connect, pool, log.clone())
                     .map_err(|e| FromConfigError::Custom(format!("unable to connect to database: {e}")))?;
                 Box::new(driver)
             }
+            "sqlite" => {
+                let mut file = db_config.get_path("file")?;
+                if file.is_relative() {
+                    file = config.root.join(&file);
+                }
+                let driver = SqliteDriver::new(file, log.clone())
+                    .map_err(|e| FromConfigError::Custom(format!("unable to open database: {e}")))?;
+                Box::new(driver)
+            }
+            "memory" => Box::new(MemoryDriver::new(log.clone())),
             ty => return Err(FromConfigError::Custom(format!("invalid database type: {:?}", ty))),
         };
 
+        let lints = match db_config.get_raw("lints") {
+            Some(_) => LintLevels::from_config(db_config.get_section("lints")?)?,
+            None => LintLevels::default(),
+        };
+
+        let type_store = TypeStore::default();
+        if db_config.get_raw("encryption").is_some() {
+            type_store.insert("Encrypted", Arc::new(Self::encryption(db_config.get_section("encryption")?)?));
+        }
+
         Ok(Context {
-            type_store: TypeStore::default(),
-            models: RefCell::default(),
-            driver: RefCell::new(driver),
+            type_store,
+            models: RwLock::default(),
+            queries: RwLock::default(),
+            driver,
+            log,
+            cache: Arc::default(),
+            lints,
             path,
         })
     }
+
+    /// An empty database in process memory, separate from every other context made this way.
+    #[cfg(test)]
+    pub(crate) fn memory() -> Self {
+        let log = Arc::new(QueryLog::default());
+        Context {
+            type_store: TypeStore::default(),
+            models: RwLock::default(),
+            queries: RwLock::default(),
+            driver: Box::new(MemoryDriver::new(log.clone())),
+            log,
+            cache: Arc::default(),
+            lints: LintLevels::default(),
+            path: PathBuf::new(),
+        }
+    }
+
+    /// `url` or the separate keys, which override the parts of the url. A `host` path is the
+    /// directory of the Unix socket.
+    fn connect_options(config: &config::ast::Section, root: &Path) -> Result<ConnectOptions, FromConfigError> {
+        let host = match config.get_raw("host") {
+            Some(_) => match config.eval("host")?.into_owned() {
+                config::ast::Value::Path(path) => Some(root.join(path).to_string_lossy().into_owned()),
+                _ => Some(config.get_string("host")?),
+            },
+            None => None,
+        };
+        let ssl_mode = match config.get_optional("ssl_mode", |s, k| s.get_string(k))? {
+            Some(name) => Some(SslMode::from_name(&name).ok_or_else(|| FromConfigError::Custom(format!(
+                "invalid database.ssl_mode: {name:?}, expected \"disable\", \"prefer\", \"require\", \"verify-ca\" or \"verify-full\"",
+            )))?),
+            None => None,
+        };
+        let connect = ConnectOptions {
+            url: config.get_optional("url", |s, k| s.get_string(k))?,
+            host,
+            port: config.get_optional("port", |s, k| s.get_int::<u16>(k))?,
+            username: config.get_optional("username", |s, k| s.get_string(k))?,
+            password: config.get_optional("password", |s, k| s.get_string(k))?,
+            database: config.get_optional("database", |s, k| s.get_string(k))?,
+            application_name: config.get_optional("application_name", |s, k| s.get_string(k))?,
+            ssl_mode,
+            ssl_root_cert: config.get_optional("ssl_root_cert", |s, k| s.get_path(k))?.map(|path| root.join(path)),
+        };
+        if connect.url.is_none() && connect.database.is_none() {
+            return Err(FromConfigError::Custom("missing database.database, or a database.url naming one".to_owned()));
+        }
+        Ok(connect)
+    }
+
+    /// `key` encrypts new values, values written with one of the `previous_keys` still decrypt
+    /// until they are written again.
+    fn encryption(config: &config::ast::Section) -> Result<EncryptedType, FromConfigError> {
+        let mut keys = vec![config.get_string("key")?];
+        if config.get_raw("previous_keys").is_some() {
+            let previous = config.get_section("previous_keys")?;
+            for name in previous.keys() {
+                keys.push(previous.get_string(name)?);
+            }
+        }
+        EncryptedType::new(&keys)
+            .map_err(|e| FromConfigError::Custom(format!("invalid database.encryption: {e}")))
+    }
+
+    /// Custom types must be registered before the qql files that use them are validated.
+    #[inline]
+    pub fn type_store(&self) -> &TypeStore {
+        &self.type_store
+    }
+
+    #[inline]
+    pub fn query_log(&self) -> &QueryLog {
+        &self.log
+    }
+
+    #[inline]
+    pub fn models(&self) -> Arc<HashMap<Ident, Model>> {
+        self.models.read().unwrap().clone()
+    }
+
+    #[inline]
+    pub fn queries(&self) -> Arc<HashMap<Ident, Query>> {
+        self.queries.read().unwrap().clone()
+    }
+
+    pub(crate) fn insert_model(&self, model: Model) {
+        let mut models = self.models.write().unwrap();
+        Arc::make_mut(&mut models).insert(model.name.clone(), model);
+    }
+
+    pub(crate) fn insert_query(&self, query: Query) {
+        let mut queries = self.queries.write().unwrap();
+        Arc::make_mut(&mut queries).insert(query.name.clone(), query);
+    }
+
+    pub async fn execute(&self, query: impl AsRef<str>, args: &[Value]) -> anyhow::Result<QueryResult> {
+        let queries = self.queries();
+        let query = Self::lookup(&queries, query.as_ref())?;
+
+        if let Some(result) = self.cache.get(query, args) {
+            return Ok(result);
+        }
+        let models = self.models();
+        rules::check_query(&models, query, args)?;
+        let generation = self.cache.generation(query);
+        let result = self.driver.execute(&models, query, args).await;
+        match (query.statement.action, &result) {
+            (Action::Select, Ok(result)) => self.cache.insert(query, args, generation, result),
+            (Action::Select, Err(_)) => {}
+            // a failed write may still have changed rows
+            _ => self.cache.invalidate(QueryCache::models(query)),
+        }
+        result
+    }
+
+    /// Writes in the transaction invalidate cached results once it commits.
+    pub async fn begin(&self) -> anyhow::Result<Box<dyn Transaction>> {
+        Ok(Box::new(InvalidatingTransaction {
+            inner: self.driver.begin().await?,
+            cache: self.cache.clone(),
+            written: HashSet::new(),
+        }))
+    }
+
+    pub async fn execute_in(
+        &self,
+        transaction: &mut dyn Transaction,
+        query: impl AsRef<str>,
+        args: &[Value],
+    ) -> anyhow::Result<QueryResult> {
+        let queries = self.queries();
+        let query = Self::lookup(&queries, query.as_ref())?;
+
+        let models = self.models();
+        rules::check_query(&models, query, args)?;
+        transaction.execute(&models, query, args).await
+    }
+
+    fn lookup<'a>(queries: &'a HashMap<Ident, Query>, name: &str) -> anyhow::Result<&'a Query> {
+        queries.get(name).ok_or_else(|| anyhow!("no query named {:?}", name))
+    }
 }
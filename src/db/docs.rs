@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+use hashbrown::HashMap;
+use crate::db::Context;
+use crate::db::ast::{Index, Model, ModelField, Query, Timestamp};
+use crate::db::parser::qql::{BinaryOp, Expr};
+use crate::db::types::{DataType, EnumType};
+use crate::db::value::Value;
+use crate::parser::Ident;
+
+/// A join between two models, `Invoice.user_id == User.user_id`. QQL has no foreign keys, so
+/// relations are the joins queries make.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Relation {
+    from: (Ident, Ident),
+    to: (Ident, Ident),
+    /// only `to` is joined on its model's first field, which identifies its rows
+    many_to_one: bool,
+}
+
+/// Writes `schema.md`, with every enum, model and relation, and the Mermaid ER diagram it embeds
+/// to `schema.mmd` in `target`.
+pub fn docs(context: &Context, target: &Path) -> anyhow::Result<()> {
+    let models = context.models();
+    let mut sorted = models.values().collect::<Vec<_>>();
+    sorted.sort_by_key(|model| &model.name);
+
+    let mut relations = BTreeMap::<Relation, BTreeSet<Ident>>::new();
+    for query in context.queries().values() {
+        for relation in query_relations(&models, query) {
+            relations.entry(relation).or_default().insert(query.name.clone());
+        }
+    }
+
+    let diagram = diagram(&sorted, &relations)?;
+
+    let mut out = String::new();
+    writeln!(&mut out, "# Database schema")?;
+
+    let mut enums = BTreeMap::<&str, &EnumType>::new();
+    for field in sorted.iter().flat_map(|model| &model.fields) {
+        if let Some(enum_) = field.repr.as_enum() {
+            enums.insert(&enum_.name, enum_);
+        }
+    }
+    if !enums.is_empty() {
+        writeln!(&mut out, "\n## Enums")?;
+        for enum_ in enums.values() {
+            let variants = enum_.variants.iter().map(|v| format!("`{v}`")).collect::<Vec<_>>();
+            writeln!(&mut out, "\n### {}\n\n{}", enum_.name, variants.join(", "))?;
+        }
+    }
+
+    writeln!(&mut out, "\n## Models")?;
+    for model in &sorted {
+        writeln!(&mut out, "\n### {}\n", model.name)?;
+        writeln!(&mut out, "| Field | Type | Optional | Constraints |")?;
+        writeln!(&mut out, "| --- | --- | --- | --- |")?;
+        for field in &model.fields {
+            writeln!(
+                &mut out,
+                "| {} | {} | {} | {} |",
+                field.name,
+                cell(&field_type(field)),
+                if field.optional { "yes" } else { "no" },
+                cell(&constraints(field).join(", ")),
+            )?;
+        }
+
+        let model_relations = relations.iter()
+            .filter(|(relation, _)| relation.from.0 == model.name || relation.to.0 == model.name)
+            .collect::<Vec<_>>();
+        if !model_relations.is_empty() {
+            writeln!(&mut out, "\nRelations:\n")?;
+            for (relation, queries) in model_relations {
+                writeln!(&mut out, "- {}", relation_line(relation, queries))?;
+            }
+        }
+    }
+
+    writeln!(&mut out, "\n## Diagram\n\n```mermaid\n{diagram}```")?;
+
+    for (name, content) in [("schema.md", &out), ("schema.mmd", &diagram)] {
+        let path = target.join(name);
+        std::fs::write(&path, content)?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn diagram(models: &[&Model], relations: &BTreeMap<Relation, BTreeSet<Ident>>) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    writeln!(&mut out, "erDiagram")?;
+    for model in models {
+        writeln!(&mut out, "    {} {{", model.name)?;
+        for field in &model.fields {
+            let key = match relations.keys().any(|r| r.from.0 == model.name && r.from.1 == field.name) {
+                true => " FK",
+                false => "",
+            };
+            let comment = match field.optional {
+                true => " \"optional\"",
+                false => "",
+            };
+            writeln!(&mut out, "        {} {}{key}{comment}", field.type_name, field.name)?;
+        }
+        writeln!(&mut out, "    }}")?;
+    }
+    for relation in relations.keys() {
+        let cardinality = match relation.many_to_one {
+            true => "}o--||",
+            false => "}o--o{",
+        };
+        writeln!(
+            &mut out,
+            "    {} {cardinality} {} : \"{} = {}\"",
+            relation.from.0, relation.to.0, relation.from.1, relation.to.1,
+        )?;
+    }
+    Ok(out)
+}
+
+/// `String(32)` or `Decimal(8, 2)`
+fn field_type(field: &ModelField) -> String {
+    match field.args.as_slice() {
+        [] => field.type_name.to_string(),
+        args => {
+            let args = args.iter().map(u64::to_string).collect::<Vec<_>>();
+            format!("{}({})", field.type_name, args.join(", "))
+        }
+    }
+}
+
+fn constraints(field: &ModelField) -> Vec<String> {
+    let mut out = Vec::new();
+    if field.repr.data_type() == DataType::UUID && field.default.is_none() {
+        out.push("generated".to_owned());
+    }
+    if let Some(default) = &field.default {
+        let default = match (default, field.repr.as_enum()) {
+            (Value::String(variant), Some(enum_)) => format!("{}.{variant}", enum_.name),
+            (Value::String(value), None) => format!("{value:?}"),
+            (value, _) => value.to_string(),
+        };
+        out.push(format!("default {default}"));
+    }
+    match field.timestamp {
+        Some(Timestamp::CreatedAt) => out.push("@created_at".to_owned()),
+        Some(Timestamp::UpdatedAt) => out.push("@updated_at".to_owned()),
+        None => {}
+    }
+    match field.index {
+        Some(Index::Plain) => out.push("@index".to_owned()),
+        Some(Index::Unique) => out.push("@unique".to_owned()),
+        Some(Index::Primary) => out.push("@primary".to_owned()),
+        None => {}
+    }
+    out.extend(field.rules.iter().map(ToString::to_string));
+    out
+}
+
+fn relation_line(relation: &Relation, queries: &BTreeSet<Ident>) -> String {
+    let queries = queries.iter().map(|query| format!("`{query}`")).collect::<Vec<_>>();
+    format!(
+        "`{}.{}` {} `{}.{}`, joined by {}",
+        relation.from.0, relation.from.1,
+        if relation.many_to_one { "references" } else { "matches" },
+        relation.to.0, relation.to.1,
+        queries.join(", "),
+    )
+}
+
+/// the text of a Markdown table cell
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Every comparison between fields of two models in the where clause or `HAVING`.
+fn query_relations(models: &HashMap<Ident, Model>, query: &Query) -> Vec<Relation> {
+    let statement = &query.statement;
+    let mut out = Vec::new();
+    let exprs = statement.where_clause.as_ref().map(|w| &w.expr).into_iter().chain(&statement.having);
+    for expr in exprs {
+        collect_relations(models, query, expr, &mut out);
+    }
+    out
+}
+
+fn collect_relations(models: &HashMap<Ident, Model>, query: &Query, expr: &Expr, out: &mut Vec<Relation>) {
+    match expr {
+        Expr::Binary(lhs, BinaryOp::Eq, rhs) => {
+            let (Expr::Field(lhs_model, lhs_field), Expr::Field(rhs_model, rhs_field)) = (lhs.as_ref(), rhs.as_ref()) else {
+                return;
+            };
+            let (Some(lhs), Some(rhs)) = (
+                resolve(models, query, lhs_model.as_ref(), lhs_field),
+                resolve(models, query, rhs_model.as_ref(), rhs_field),
+            ) else {
+                return;
+            };
+            if lhs.0.name == rhs.0.name {
+                return;
+            }
+
+            let identifies = |(model, field): (&Model, &Ident)| model.fields.first().is_some_and(|f| f.name == *field);
+            let (from, to) = match (identifies(lhs), identifies(rhs)) {
+                (true, false) => (rhs, lhs),
+                (false, true) => (lhs, rhs),
+                // without a side to refer to, the relation is written in name order
+                _ if lhs.0.name <= rhs.0.name => (lhs, rhs),
+                _ => (rhs, lhs),
+            };
+            out.push(Relation {
+                many_to_one: identifies(lhs) != identifies(rhs),
+                from: (from.0.name.clone(), from.1.clone()),
+                to: (to.0.name.clone(), to.1.clone()),
+            });
+        }
+        Expr::Binary(lhs, _, rhs) => {
+            collect_relations(models, query, lhs, out);
+            collect_relations(models, query, rhs, out);
+        }
+        Expr::Unary(_, inner) | Expr::Group(inner) => collect_relations(models, query, inner, out),
+        _ => {}
+    }
+}
+
+/// the model of a field, which selector it belongs to when the query does not name it
+fn resolve<'a>(
+    models: &'a HashMap<Ident, Model>,
+    query: &Query,
+    model: Option<&Ident>,
+    field: &'a Ident,
+) -> Option<(&'a Model, &'a Ident)> {
+    query.statement.selectors.iter()
+        .filter(|selector| model.is_none_or(|model| *model == selector.name))
+        .find_map(|selector| {
+            let model = models.get(&selector.name)?;
+            model.has_field(field).then_some((model, field))
+        })
+}
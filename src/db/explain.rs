@@ -0,0 +1,250 @@
+use std::fmt::{Display, Formatter};
+use anyhow::{anyhow, bail};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use crate::db::Context;
+use crate::db::ast::{Model, Query};
+use crate::db::backend::{PlanNode, QueryPlan};
+use crate::db::parser::qql::{Expr, Quantifier};
+use crate::db::types::DataType;
+use crate::db::value::Value;
+use crate::parser::Ident;
+
+/// The plan of a query, with a warning for every table read in full although the query filters
+/// it on fields without an index.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub plan: QueryPlan,
+    pub warnings: Vec<String>,
+}
+
+/// Explains the registered query `name`. Without `args` every argument is a placeholder of the
+/// type of the field it is compared with, the database plans for those values.
+pub async fn explain(context: &Context, name: &str, args: Option<Vec<Value>>, analyze: bool) -> anyhow::Result<Explanation> {
+    let models = context.models();
+    let queries = context.queries();
+    let query = queries.get(name)
+        .ok_or_else(|| anyhow!("no query named {:?} in {}", name, context.path.display()))?;
+    let args = match args {
+        Some(args) if args.len() != query.args.len() => {
+            bail!("query {name} takes {} arguments, found {}", query.args.len(), args.len());
+        }
+        Some(args) => args,
+        None => placeholders(&models, query),
+    };
+
+    let plan = context.driver.explain(&models, query, &args, analyze).await?;
+    let mut warnings = Vec::new();
+    for node in &plan.nodes {
+        full_scans(&models, query, node, &mut warnings);
+    }
+    Ok(Explanation { plan, warnings })
+}
+
+fn full_scans(models: &HashMap<Ident, Model>, query: &Query, node: &PlanNode, warnings: &mut Vec<String>) {
+    if let Some(model) = node.full_scan.then_some(node.table.as_ref()).flatten().and_then(|table| models.get(table.as_str())) {
+        let unindexed = filtered_fields(models, query, model).into_iter()
+            .filter(|field| model.get_field(field).is_some_and(|field| field.index.is_none()))
+            .collect::<Vec<_>>();
+        let message = match unindexed.as_slice() {
+            [] => None,
+            [field] => Some(format!("{}.{field} has no index", model.name)),
+            fields => Some(format!("{}.{{{}}} have no index", model.name, fields.join(", "))),
+        };
+        if let Some(message) = message {
+            warnings.push(format!(
+                "{} reads every row of {} to filter it, {message}, declare one with @index or @unique",
+                node.operation, model.name,
+            ));
+        }
+    }
+    for child in &node.children {
+        full_scans(models, query, child, warnings);
+    }
+}
+
+/// The fields of `model` the where clause compares in the database, in the order they appear.
+fn filtered_fields(models: &HashMap<Ident, Model>, query: &Query, model: &Model) -> Vec<Ident> {
+    fn collect(models: &HashMap<Ident, Model>, query: &Query, model: &Model, expr: &Expr, out: &mut Vec<Ident>) {
+        match expr {
+            Expr::Field(field_model, field) => {
+                let resolved = resolve(models, query, field_model.as_ref(), field)
+                    .filter(|(resolved, _)| resolved.name == model.name)
+                    .and_then(|(model, field)| model.get_field(field));
+                if let Some(field) = resolved.filter(|field| !field.repr.compared_in_rust()) {
+                    if !out.contains(&field.name) {
+                        out.push(field.name.clone());
+                    }
+                }
+            }
+            Expr::Binary(lhs, _, rhs) => {
+                collect(models, query, model, lhs, out);
+                collect(models, query, model, rhs, out);
+            }
+            Expr::Unary(_, inner) | Expr::Group(inner) | Expr::IsNull(inner, _) | Expr::InArgument(inner, _) => {
+                collect(models, query, model, inner, out);
+            }
+            Expr::In(inner, values) => {
+                collect(models, query, model, inner, out);
+                for value in values {
+                    collect(models, query, model, value, out);
+                }
+            }
+            Expr::Between(inner, low, high) => {
+                for expr in [inner, low, high] {
+                    collect(models, query, model, expr, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    if let Some(where_clause) = &query.statement.where_clause {
+        collect(models, query, model, &where_clause.expr, &mut out);
+    }
+    out
+}
+
+/// A value for every argument of `query`, typed by the field it is compared with or written to.
+/// Arguments limiting or skipping rows are small numbers, the others are null.
+fn placeholders(models: &HashMap<Ident, Model>, query: &Query) -> Vec<Value> {
+    fn collect(models: &HashMap<Ident, Model>, query: &Query, expr: &Expr, out: &mut HashMap<Ident, Value>) {
+        let field_value = |model: &Option<Ident>, field: &Ident| {
+            resolve(models, query, model.as_ref(), field)
+                .and_then(|(model, field)| model.get_field(field))
+                .map(|field| placeholder(field.repr.data_type(), field.repr.as_enum().and_then(|e| e.variants.first())))
+        };
+        match expr {
+            Expr::Binary(lhs, _, rhs) => match (lhs.as_ref(), rhs.as_ref()) {
+                (Expr::Field(model, field), Expr::Interp(arg)) | (Expr::Interp(arg), Expr::Field(model, field)) => {
+                    if let Some(value) = field_value(model, field) {
+                        out.entry(arg.clone()).or_insert(value);
+                    }
+                }
+                (lhs, rhs) => {
+                    collect(models, query, lhs, out);
+                    collect(models, query, rhs, out);
+                }
+            },
+            Expr::Between(inner, low, high) => {
+                if let Expr::Field(model, field) = inner.as_ref() {
+                    for bound in [low, high] {
+                        if let (Expr::Interp(arg), Some(value)) = (bound.as_ref(), field_value(model, field)) {
+                            out.entry(arg.clone()).or_insert(value);
+                        }
+                    }
+                }
+            }
+            Expr::InArgument(inner, arg) => {
+                // a single element, an empty list compiles to `false` and hides the plan
+                let element = match inner.as_ref() {
+                    Expr::Field(model, field) => field_value(model, field).map(|value| match value {
+                        Value::Int(value) => serde_json::Value::from(value),
+                        Value::Bool(value) => serde_json::Value::from(value),
+                        value => serde_json::Value::from(value.to_string()),
+                    }),
+                    _ => None,
+                };
+                out.entry(arg.clone()).or_insert(Value::Json(serde_json::Value::Array(element.into_iter().collect())));
+            }
+            Expr::Unary(_, inner) | Expr::Group(inner) | Expr::IsNull(inner, _) => collect(models, query, inner, out),
+            _ => {}
+        }
+    }
+
+    let statement = &query.statement;
+    let mut values = HashMap::new();
+    let exprs = statement.where_clause.iter().map(|w| &w.expr).chain(&statement.having);
+    for expr in exprs {
+        collect(models, query, expr, &mut values);
+    }
+    for selector in &statement.selectors {
+        for selector_field in &selector.fields {
+            let (Some(Expr::Interp(arg)), Some(field)) = (
+                &selector_field.value,
+                models.get(&selector.name).and_then(|model| model.get_field(&selector_field.name)),
+            ) else {
+                continue;
+            };
+            let variant = field.repr.as_enum().and_then(|e| e.variants.first());
+            values.entry(arg.clone()).or_insert(placeholder(field.repr.data_type(), variant));
+        }
+    }
+    if let Quantifier::Expr(Expr::Interp(arg)) = &statement.quantifier {
+        values.entry(arg.clone()).or_insert(Value::Int(10));
+    }
+    if let Some(Expr::Interp(arg)) = &statement.offset {
+        values.entry(arg.clone()).or_insert(Value::Int(0));
+    }
+
+    query.args.iter()
+        .map(|arg| values.remove(arg).unwrap_or(Value::Null))
+        .collect()
+}
+
+/// An ordinary value of a type, enums take their first variant.
+fn placeholder(data_type: DataType, variant: Option<&String>) -> Value {
+    match data_type {
+        DataType::UUID => Value::Uuid(Uuid::nil()),
+        DataType::String => Value::String(String::new()),
+        DataType::Enum => Value::String(variant.cloned().unwrap_or_default()),
+        DataType::DateTime => Value::DateTime(NaiveDateTime::default()),
+        DataType::Date => Value::Date(NaiveDate::default()),
+        DataType::Time => Value::Time(NaiveTime::default()),
+        DataType::Int | DataType::BigInt => Value::Int(0),
+        DataType::Bool => Value::Bool(false),
+        DataType::Decimal => Value::Decimal(Decimal::ZERO),
+        DataType::Float => Value::Float(0.0),
+        DataType::Duration => Value::Duration(TimeDelta::zero()),
+        DataType::Json => Value::Json(serde_json::Value::Null),
+        DataType::Bytes => Value::Bytes(Vec::new()),
+    }
+}
+
+/// the model of a field, which selector it belongs to when the query does not name it
+fn resolve<'a>(
+    models: &'a HashMap<Ident, Model>,
+    query: &Query,
+    model: Option<&Ident>,
+    field: &'a Ident,
+) -> Option<(&'a Model, &'a Ident)> {
+    query.statement.selectors.iter()
+        .filter(|selector| model.is_none_or(|model| *model == selector.name))
+        .find_map(|selector| {
+            let model = models.get(&selector.name)?;
+            model.has_field(field).then_some((model, field))
+        })
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn write_node(f: &mut Formatter<'_>, node: &PlanNode, depth: usize) -> std::fmt::Result {
+            let indent = "    ".repeat(depth);
+            match depth {
+                0 => writeln!(f, "{}", node.operation)?,
+                _ => writeln!(f, "{indent}-> {}", node.operation)?,
+            }
+            for detail in &node.details {
+                writeln!(f, "{indent}     {detail}")?;
+            }
+            for child in &node.children {
+                write_node(f, child, depth + 1)?;
+            }
+            Ok(())
+        }
+
+        for root in &self.plan.nodes {
+            write_node(f, root, 0)?;
+        }
+        for note in &self.plan.notes {
+            writeln!(f, "{note}")?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "warning: {warning}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt::{Display, Formatter};
+use hashbrown::{HashMap, HashSet};
+use crate::config::ast::Section;
+use crate::config::error::FromConfigError;
+use crate::db::Context;
+use crate::db::ast::{Model, Query};
+use crate::db::parser::qql::{BinaryOp, Expr};
+use crate::db::types::DataType;
+use crate::parser::Ident;
+
+/// A schema problem `aio check` reports. Severities are set in the `lints` section of the
+/// database config:
+///
+/// ```text
+/// lints {
+///     unbounded_string "allow"
+///     unused_query "deny"
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// a model without a `@primary` field
+    MissingPrimaryKey,
+    /// `String` without a maximum length
+    UnboundedString,
+    /// a model or field name sql only reads as written when quoted
+    QuotedIdentifier,
+    /// `field == #argument` on an optional field, which never matches null
+    OptionalEquality,
+    /// a query no route or shared file calls
+    UnusedQuery,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::MissingPrimaryKey,
+        Lint::UnboundedString,
+        Lint::QuotedIdentifier,
+        Lint::OptionalEquality,
+        Lint::UnusedQuery,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::MissingPrimaryKey => "missing_primary_key",
+            Lint::UnboundedString => "unbounded_string",
+            Lint::QuotedIdentifier => "quoted_identifier",
+            Lint::OptionalEquality => "optional_equality",
+            Lint::UnusedQuery => "unused_query",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Allow,
+    Warn,
+    /// fails `aio check`
+    Deny,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Severity::Allow),
+            "warn" => Some(Severity::Warn),
+            "deny" => Some(Severity::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Every lint warns unless the config says otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    levels: HashMap<Lint, Severity>,
+}
+
+impl LintLevels {
+    pub fn from_config(config: &Section) -> Result<Self, FromConfigError> {
+        let mut levels = HashMap::new();
+        for name in config.keys() {
+            let lint = Lint::from_name(name).ok_or_else(|| FromConfigError::Custom(format!(
+                "invalid database.lints: unknown lint {name:?}, expected one of {}",
+                Lint::ALL.map(|lint| lint.name()).join(", "),
+            )))?;
+            let severity = config.get_string(name)?;
+            let severity = Severity::from_name(&severity).ok_or_else(|| FromConfigError::Custom(format!(
+                "invalid database.lints.{name}: {severity:?}, expected \"allow\", \"warn\" or \"deny\"",
+            )))?;
+            levels.insert(lint, severity);
+        }
+        Ok(LintLevels { levels })
+    }
+
+    pub fn severity(&self, lint: Lint) -> Severity {
+        self.levels.get(&lint).copied().unwrap_or(Severity::Warn)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub lint: Lint,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let level = match self.severity {
+            Severity::Deny => "error",
+            _ => "warning",
+        };
+        write!(f, "{level}[{}]: {}", self.lint.name(), self.message)
+    }
+}
+
+/// Checks the validated models and queries, `used_queries` are the qualified names the web code
+/// calls. Allowed lints are left out, models and queries are reported in name order.
+pub fn lint(context: &Context, used_queries: &HashSet<String>) -> Vec<Finding> {
+    let levels = &context.lints;
+    let mut findings = Vec::new();
+    let mut report = |lint: Lint, message: String| {
+        let severity = levels.severity(lint);
+        if severity != Severity::Allow {
+            findings.push(Finding { lint, severity, message });
+        }
+    };
+
+    let models = context.models();
+    let mut sorted_models = models.values().collect::<Vec<_>>();
+    sorted_models.sort_by(|a, b| a.name.cmp(&b.name));
+    for model in sorted_models {
+        if model.primary_key().is_none() {
+            report(Lint::MissingPrimaryKey, format!("model {} has no field marked @primary", model.name));
+        }
+        if needs_quoting(&model.name, true) {
+            report(Lint::QuotedIdentifier, format!(
+                "model {} is an sql keyword, sql written by hand must quote the table name",
+                model.name,
+            ));
+        }
+        for field in &model.fields {
+            if field.repr.data_type() == DataType::String && field.args.is_empty() && !field.repr.compared_in_rust() {
+                report(Lint::UnboundedString, format!(
+                    "{}.{} has no maximum length, write {}(n)",
+                    model.name, field.name, field.type_name,
+                ));
+            }
+            if needs_quoting(&field.name, false) {
+                report(Lint::QuotedIdentifier, format!(
+                    "{}.{} is an sql keyword or not lowercase, sql written by hand must quote it",
+                    model.name, field.name,
+                ));
+            }
+        }
+    }
+
+    let queries = context.queries();
+    let mut sorted_queries = queries.iter().collect::<Vec<_>>();
+    sorted_queries.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, query) in sorted_queries {
+        if let Some(where_clause) = &query.statement.where_clause {
+            for (model, field) in optional_equalities(&models, query, &where_clause.expr) {
+                report(Lint::OptionalEquality, format!(
+                    "query {name} compares the optional field {model}.{field} with an argument, rows \
+                    where it is null never match, use IS NULL for those",
+                ));
+            }
+        }
+        if !used_queries.contains(name.as_str()) {
+            report(Lint::UnusedQuery, format!("query {name} is not called by any route or shared file"));
+        }
+    }
+
+    findings
+}
+
+/// `field == #argument` and `field != #argument` on optional fields.
+fn optional_equalities(models: &HashMap<Ident, Model>, query: &Query, expr: &Expr) -> Vec<(Ident, Ident)> {
+    let mut out = Vec::new();
+    match expr {
+        Expr::Binary(lhs, BinaryOp::Eq | BinaryOp::Ne, rhs) => {
+            if let (Expr::Field(model, field), Expr::Interp(_)) | (Expr::Interp(_), Expr::Field(model, field)) = (lhs.as_ref(), rhs.as_ref()) {
+                let field = query.statement.selectors.iter()
+                    .filter(|selector| model.as_ref().is_none_or(|model| *model == selector.name))
+                    .find_map(|selector| models.get(&selector.name)?.get_field(field).map(|f| (&selector.name, f)));
+                if let Some((model, field)) = field.filter(|(_, field)| field.optional) {
+                    out.push((model.clone(), field.name.clone()));
+                }
+            }
+        }
+        Expr::Binary(lhs, _, rhs) => {
+            out.extend(optional_equalities(models, query, lhs));
+            out.extend(optional_equalities(models, query, rhs));
+        }
+        Expr::Unary(_, inner) | Expr::Group(inner) => out.extend(optional_equalities(models, query, inner)),
+        _ => {}
+    }
+    out
+}
+
+/// The drivers quote every name, see `db::quote`, but sql written by hand has to as well.
+/// Postgres folds unquoted names to lowercase, which only matters for fields since models are
+/// conventionally capitalized.
+fn needs_quoting(name: &str, quoted: bool) -> bool {
+    let keyword = RESERVED_WORDS.contains(&name.to_ascii_lowercase().as_str());
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    keyword || (!quoted && !plain)
+}
+
+/// Reserved by postgres or sqlite, they cannot be used as column names without quotes.
+const RESERVED_WORDS: &[&str] = &[
+    "all", "alter", "analyse", "analyze", "and", "any", "array", "as", "asc", "asymmetric",
+    "begin", "between", "both", "case", "cast", "check", "collate", "column", "commit",
+    "constraint", "create", "cross", "current_catalog", "current_date", "current_role",
+    "current_time", "current_timestamp", "current_user", "default", "deferrable", "delete", "desc",
+    "distinct", "do", "drop", "else", "end", "escape", "except", "exists", "false", "fetch", "for",
+    "foreign", "from", "full", "grant", "group", "having", "in", "index", "initially", "inner",
+    "insert", "intersect", "into", "is", "join", "lateral", "leading", "left", "like", "limit",
+    "localtime", "localtimestamp", "natural", "not", "notnull", "null", "offset", "on", "only",
+    "or", "order", "outer", "placing", "primary", "references", "returning", "right", "rollback",
+    "select", "session_user", "set", "some", "symmetric", "system_user", "table", "then", "to",
+    "trailing", "transaction", "true", "union", "unique", "update", "user", "using", "values",
+    "variadic", "when", "where", "window", "with",
+];
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::Local;
+use serde_derive::{Deserialize, Serialize};
+use crate::db::value::Value;
+
+/// Which statements are written to stderr, slow ones are always written as warnings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogMode {
+    #[default]
+    Off,
+    /// the number of parameters, but not their values
+    On,
+    /// every parameter value, including passwords
+    Values,
+}
+
+impl LogMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(LogMode::Off),
+            "on" => Some(LogMode::On),
+            "values" => Some(LogMode::Values),
+            _ => None,
+        }
+    }
+}
+
+/// Times every statement the driver runs, failed ones included, and keeps per query totals for
+/// `aio db stats`.
+#[derive(Debug, Default)]
+pub struct QueryLog {
+    pub(crate) mode: LogMode,
+    /// statements taking at least this long are warnings
+    pub(crate) slow: Option<Duration>,
+    pub(crate) stats: Mutex<SessionStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// local time, `2024-05-01 12:00:00`
+    pub started: String,
+    pub queries: BTreeMap<String, QueryStats>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        SessionStats {
+            started: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            queries: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub calls: u64,
+    pub rows: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    pub slow: u64,
+    /// stats saved before failures were counted have none
+    #[serde(default)]
+    pub failed: u64,
+}
+
+impl QueryLog {
+    pub fn new(mode: LogMode, slow: Option<Duration>) -> Self {
+        QueryLog {
+            mode,
+            slow,
+            stats: Mutex::default(),
+        }
+    }
+
+    /// `sql` is `None` for drivers that do not compile queries, `rows` is the error of a failed
+    /// statement.
+    pub fn record(&self, query: &str, sql: Option<&str>, params: &[Value], rows: Result<u64, &anyhow::Error>, elapsed: Duration) {
+        self.write(query, sql, Some(params), rows, elapsed);
+    }
+
+    /// Records a statement that is not a query under `name`: a migration, `begin`, `commit` or
+    /// `rollback`.
+    pub fn record_statement(&self, name: &str, sql: Option<&str>, result: Result<(), &anyhow::Error>, elapsed: Duration) {
+        self.write(name, sql, None, result.map(|_| 0), elapsed);
+    }
+
+    fn write(&self, name: &str, sql: Option<&str>, params: Option<&[Value]>, rows: Result<u64, &anyhow::Error>, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let slow = self.slow.is_some_and(|slow| elapsed >= slow);
+
+        {
+            let mut session = self.stats.lock().unwrap();
+            let stats = session.queries.entry(name.to_owned()).or_default();
+            stats.calls += 1;
+            stats.rows += rows.as_ref().copied().unwrap_or(0);
+            stats.total_ms += ms;
+            stats.max_ms = stats.max_ms.max(ms);
+            stats.slow += slow as u64;
+            stats.failed += rows.is_err() as u64;
+        }
+
+        if !slow && self.mode == LogMode::Off {
+            return;
+        }
+        let mut line = if slow { "warning: slow ".to_owned() } else { String::new() };
+        line.push_str(if params.is_some() { "query" } else { "statement" });
+        match (rows, params) {
+            (Err(error), _) => line.push_str(&format!(" {name}: failed in {ms:.2}ms with {error:#}")),
+            (Ok(1), Some(_)) => line.push_str(&format!(" {name}: 1 row in {ms:.2}ms")),
+            (Ok(rows), Some(_)) => line.push_str(&format!(" {name}: {rows} rows in {ms:.2}ms")),
+            (Ok(_), None) => line.push_str(&format!(" {name}: {ms:.2}ms")),
+        }
+        match params {
+            Some(params) if self.mode == LogMode::Values => {
+                let values = params.iter().map(ToString::to_string).collect::<Vec<_>>();
+                line.push_str(&format!(", [{}]", values.join(", ")));
+            }
+            Some([_]) => line.push_str(", 1 param"),
+            Some(params) => line.push_str(&format!(", {} params", params.len())),
+            None => {}
+        }
+        if let Some(sql) = sql {
+            line.push_str(&format!(": {sql}"));
+        }
+        eprintln!("{line}");
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(&self.stats())?)?;
+        Ok(())
+    }
+}
+
+impl SessionStats {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// A table of every query, the ones that took the longest in total first.
+    pub fn summary(&self) -> String {
+        use std::fmt::Write;
+
+        let mut queries = self.queries.iter().collect::<Vec<_>>();
+        queries.sort_by(|a, b| b.1.total_ms.total_cmp(&a.1.total_ms));
+
+        let calls = self.queries.values().map(|stats| stats.calls).sum::<u64>();
+        let total_ms = self.queries.values().map(|stats| stats.total_ms).sum::<f64>();
+        let mut out = String::new();
+        writeln!(
+            &mut out,
+            "{calls} statements in {total_ms:.2}ms since {}",
+            self.started,
+        ).unwrap();
+        if queries.is_empty() {
+            return out;
+        }
+
+        let width = queries.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("query".len());
+        writeln!(
+            &mut out,
+            "\n{:width$}  {:>7}  {:>9}  {:>10}  {:>9}  {:>9}  {:>5}  {:>6}",
+            "query", "calls", "rows", "total ms", "mean ms", "max ms", "slow", "failed",
+        ).unwrap();
+        for (name, stats) in queries {
+            writeln!(
+                &mut out,
+                "{name:width$}  {:>7}  {:>9}  {:>10.2}  {:>9.2}  {:>9.2}  {:>5}  {:>6}",
+                stats.calls,
+                stats.rows,
+                stats.total_ms,
+                stats.total_ms / stats.calls.max(1) as f64,
+                stats.max_ms,
+                stats.slow,
+                stats.failed,
+            ).unwrap();
+        }
+        out
+    }
+}
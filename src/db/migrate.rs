@@ -1,17 +1,15 @@
 use crate::db::Context;
 
-pub fn migrate_up(context: &Context) -> anyhow::Result<()> {
-    let mut driver = context.driver.borrow_mut();
-    for model in context.models.borrow().values() {
-        driver.migrate_up(model)?;
+pub async fn migrate_up(context: &Context) -> anyhow::Result<()> {
+    for model in context.models().values() {
+        context.driver.migrate_up(model).await?;
     }
     Ok(())
 }
 
-pub fn migrate_down(context: &Context) -> anyhow::Result<()> {
-    let mut driver = context.driver.borrow_mut();
-    for model in context.models.borrow().values() {
-        driver.migrate_down(model)?;
+pub async fn migrate_down(context: &Context) -> anyhow::Result<()> {
+    for model in context.models().values() {
+        context.driver.migrate_down(model).await?;
     }
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,9 +1,22 @@
 pub mod ast;
 pub mod backend;
+pub mod cache;
+pub mod codegen;
+pub mod compile;
 pub mod context;
+pub mod docs;
+pub mod explain;
+pub mod lint;
+pub mod log;
 pub mod migrate;
 pub mod parser;
+pub mod pull;
+pub mod quote;
+pub mod rules;
+pub mod seed;
+pub mod transfer;
 pub mod types;
 pub mod validate;
+pub mod value;
 
 pub use context::Context;
\ No newline at end of file
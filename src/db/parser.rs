@@ -5,6 +5,8 @@ use std::ops::Deref;
 use std::str::FromStr;
 use hashbrown::HashMap;
 use crate::parser::{Ident, ParseError, ParsePrimitive, Parser};
+pub use call::*;
+pub use enums::*;
 pub use model::*;
 pub use query::*;
 
@@ -37,11 +39,13 @@ impl<'a> QQLParser<'a> {
         while !self.inner.at_end() {
             if let Some(model) = self.parse_model()? {
                 out.models.insert(model.name.clone(), model);
+            } else if let Some(enum_) = self.parse_enum()? {
+                out.enums.insert(enum_.name.clone(), enum_);
             } else if let Some(query) = self.parse_query()? {
                 out.queries.insert(query.name.clone(), query);
             } else {
                 return Err(ParseError::new(
-                    "expected model or query",
+                    "expected model, enum, or query",
                     self.location,
                 ));
             }
@@ -78,6 +82,64 @@ impl<'a> QQLParser<'a> {
         })
     }
 
+    pub fn parse_string(&mut self) -> Result<Option<String>, ParseError> {
+        self.inner.atomic(|parser| {
+            let string_type;
+            if parser.take('"') {
+                string_type = '"';
+            } else if parser.take('\'') {
+                string_type = '\'';
+            } else {
+                return Ok(None);
+            }
+
+            let start = parser.location;
+            let mut previous_content_index = start.index;
+            let mut content = Option::<String>::None;
+            while !parser.at_end() && !parser.peek(string_type) {
+                if parser.take(|c| c != '\\') {
+                    continue;
+                }
+
+                // we need to fill the content string with previous normal characters
+                let content = content.get_or_insert_with(String::new);
+                content.push_str(&parser.source[previous_content_index..parser.location.index]);
+
+                let escape_location = parser.location;
+                parser.location.advance('\\');
+                let escaped_char = parser.take_char()
+                    .ok_or_else(|| ParseError::new("unterminated string, expected escape character after '\\'", parser.location))?;
+
+                match escaped_char {
+                    'n' => content.push('\n'),
+                    'r' => content.push('\r'),
+                    't' => content.push('\t'),
+                    '\\' => content.push('\\'),
+                    '\'' => content.push('\''),
+                    '"' => content.push('"'),
+                    _ => return Err(ParseError::new_spanned(
+                        "invalid escape character",
+                        escape_location,
+                        parser.location.index - escape_location.index,
+                    )),
+                };
+
+                previous_content_index = parser.location.index;
+            }
+            parser.expect(string_type)?;
+
+            let content = match content {
+                Some(mut content) => {
+                    content.push_str(&parser.source[previous_content_index..parser.location.index - 1]);
+                    content
+                }
+                None => parser.source[start.index..parser.location.index - 1].to_owned(),
+            };
+
+            Ok(Some(content))
+        })
+    }
+
     pub fn parse_ident(&mut self) -> Option<Ident> {
         self.inner.atomic(|parser| {
             let start = parser.location;
@@ -178,6 +240,7 @@ impl<'a> QQLParser<'a> {
 #[derive(Default, Debug)]
 pub struct QQLFile {
     pub models: HashMap<Ident, Model>,
+    pub enums: HashMap<Ident, Enum>,
     pub queries: HashMap<Ident, Query>,
 }
 
@@ -190,7 +253,42 @@ impl FromStr for QQLFile {
     }
 }
 
+pub mod enums {
+    use crate::parser::{Ident, ParseError};
+
+    #[derive(Debug)]
+    pub struct Enum {
+        pub name: Ident,
+        pub variants: Vec<Ident>,
+    }
+
+    impl<'a> super::QQLParser<'a> {
+        pub fn parse_enum(&mut self) -> Result<Option<Enum>, ParseError> {
+            if !self.take_keyword("enum") {
+                return Ok(None);
+            }
+
+            let Some(name) = self.parse_ident() else {
+                return Err(ParseError::new("expected enum name", self.inner.location));
+            };
+
+            self.inner.expect('{')?;
+            let variants = self.parse_separated_terminated('}', ',', |parser| {
+                parser.parse_ident()
+                    .ok_or_else(|| ParseError::new("expected enum variant", parser.inner.location))
+            })?;
+            self.inner.expect('}')?;
+
+            Ok(Some(Enum {
+                name,
+                variants,
+            }))
+        }
+    }
+}
+
 pub mod model {
+    use crate::db::value::Value;
     use crate::parser::{Ident, ParseError};
 
     #[derive(Debug)]
@@ -224,6 +322,8 @@ pub mod model {
     pub struct ModelField {
         pub name: Ident,
         pub type_: FieldType,
+        pub default: Option<FieldDefault>,
+        pub attributes: Vec<Attribute>,
     }
 
     impl<'a> super::QQLParser<'a> {
@@ -232,15 +332,74 @@ pub mod model {
                 .ok_or_else(|| ParseError::new("expected field name", self.inner.location))?;
             self.inner.expect(":")?;
             let type_ = self.parse_field_type()?;
+            let default = match self.inner.take('=') {
+                true => Some(self.parse_field_default()?),
+                false => None,
+            };
+
+            let mut attributes = Vec::new();
+            while let Some(attribute) = self.parse_attribute()? {
+                attributes.push(attribute);
+            }
 
-            Ok(ModelField { name, type_ })
+            Ok(ModelField { name, type_, default, attributes })
+        }
+    }
+
+    /// The value an insert uses when it does not set the field, `= 0` or `= Role.Member`
+    #[derive(Debug)]
+    pub enum FieldDefault {
+        Literal(Value),
+        Variant(Ident, Ident),
+    }
+
+    impl<'a> super::QQLParser<'a> {
+        pub fn parse_field_default(&mut self) -> Result<FieldDefault, ParseError> {
+            let location = self.inner.location;
+            if let Some(name) = self.parse_ident() {
+                if self.inner.take('.') {
+                    let variant = self.parse_ident()
+                        .ok_or_else(|| ParseError::new("expected variant name", self.inner.location))?;
+                    return Ok(FieldDefault::Variant(name, variant));
+                }
+                // `true`, `false`, and `null`
+                self.inner.location = location;
+            }
+            self.parse_call_argument().map(FieldDefault::Literal)
+        }
+    }
+
+    /// `@created_at` or `@name(arguments)` after a field's type or a query's arguments
+    #[derive(Debug, Clone)]
+    pub struct Attribute {
+        pub name: Ident,
+        pub args: Vec<Value>,
+    }
+
+    impl<'a> super::QQLParser<'a> {
+        pub fn parse_attribute(&mut self) -> Result<Option<Attribute>, ParseError> {
+            if !self.inner.take('@') {
+                return Ok(None);
+            }
+
+            let name = self.parse_ident()
+                .ok_or_else(|| ParseError::new("expected attribute name", self.inner.location))?;
+            let args = if self.inner.take('(') {
+                let args = self.parse_separated_terminated(')', ',', Self::parse_call_argument)?;
+                self.inner.expect(')')?;
+                args
+            } else {
+                Vec::new()
+            };
+
+            Ok(Some(Attribute { name, args }))
         }
     }
 
     #[derive(Debug)]
     pub struct FieldType {
         pub name: Ident,
-        pub arg: Option<u64>,
+        pub args: Vec<u64>,
         pub optional: bool,
     }
 
@@ -249,20 +408,22 @@ pub mod model {
             let name = self.parse_ident()
                 .ok_or_else(|| ParseError::new("expected type name", self.inner.location))?;
 
-            let arg = if self.inner.take('(') {
-                let value = self.parse_number::<u64>()?
-                    .ok_or_else(|| ParseError::new("expected type argument", self.inner.location))?;
+            let args = if self.inner.take('(') {
+                let args = self.parse_separated_terminated(')', ',', |parser| {
+                    parser.parse_number::<u64>()?
+                        .ok_or_else(|| ParseError::new("expected type argument", parser.inner.location))
+                })?;
                 self.inner.expect(')')?;
-                Some(value)
+                args
             } else {
-                None
+                Vec::new()
             };
 
             let optional = self.inner.take('?');
 
             Ok(FieldType {
                 name,
-                arg,
+                args,
                 optional,
             })
         }
@@ -270,13 +431,16 @@ pub mod model {
 }
 
 pub mod query {
+    use crate::db::parser::model::Attribute;
     use crate::db::parser::qql::Statement;
     use crate::parser::{Ident, ParseError};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Query {
         pub name: Ident,
         pub args: Vec<Ident>,
+        /// `@cache(60s)` between the arguments and the body
+        pub attributes: Vec<Attribute>,
         pub statement: Statement,
     }
 
@@ -295,6 +459,10 @@ pub mod query {
                     .ok_or_else(|| ParseError::new("expected identifier", parser.inner.location)),
             )?;
             self.inner.expect(')')?;
+            let mut attributes = Vec::new();
+            while let Some(attribute) = self.parse_attribute()? {
+                attributes.push(attribute);
+            }
             self.inner.expect('{')?;
             let statement = self.parse_qql_statement()?;
             self.inner.expect('}')?;
@@ -302,22 +470,130 @@ pub mod query {
             Ok(Some(Query {
                 name,
                 args,
+                attributes,
                 statement,
             }))
         }
     }
 }
 
+pub mod call {
+    use std::str::FromStr;
+    use chrono::TimeDelta;
+    use crate::db::value::Value;
+    use crate::parser::{Ident, ParseError};
+
+    /// A query invocation such as `user.Login("bob", "hunter2")`
+    #[derive(Debug)]
+    pub struct QueryCall {
+        pub path: Vec<Ident>,
+        pub name: Ident,
+        pub args: Vec<Value>,
+    }
+
+    impl QueryCall {
+        /// The name the query is registered under, `billing.invoice.Unpaid`.
+        pub fn qualified_name(&self) -> String {
+            self.path.iter()
+                .chain([&self.name])
+                .map(|ident| ident.as_str())
+                .collect::<Vec<_>>()
+                .join(".")
+        }
+    }
+
+    impl FromStr for QueryCall {
+        type Err = ParseError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut parser = super::QQLParser::new(s);
+            let call = parser.parse_query_call()?;
+            if !parser.inner.at_end() {
+                return Err(ParseError::new("unexpected input after query call", parser.inner.location));
+            }
+            Ok(call)
+        }
+    }
+
+    impl<'a> super::QQLParser<'a> {
+        pub fn parse_query_call(&mut self) -> Result<QueryCall, ParseError> {
+            let mut path = self.parse_separated('.', |parser| {
+                parser.parse_ident()
+                    .ok_or_else(|| ParseError::new("expected identifier", parser.inner.location))
+            })?;
+            let name = path.pop().unwrap();
+
+            self.inner.expect('(')?;
+            let args = self.parse_separated_terminated(')', ',', Self::parse_call_argument)?;
+            self.inner.expect(')')?;
+
+            Ok(QueryCall { path, name, args })
+        }
+
+        pub(crate) fn parse_call_argument(&mut self) -> Result<Value, ParseError> {
+            if let Some(string) = self.parse_string()? {
+                return Ok(Value::String(string));
+            }
+
+            let negative = self.inner.take('-');
+            if let Some(number) = self.parse_number::<i64>()? {
+                let number = if negative { -number } else { number };
+                // `60s`, the unit follows the number without whitespace
+                if self.inner.peek_char().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    return self.parse_duration_unit(number).map(Value::Duration);
+                }
+                return Ok(Value::Int(number));
+            }
+
+            if self.take_keyword("null") {
+                return Ok(Value::Null);
+            }
+            if self.take_keyword("true") {
+                return Ok(Value::Bool(true));
+            }
+            if self.take_keyword("false") {
+                return Ok(Value::Bool(false));
+            }
+
+            Err(ParseError::new("expected string, number, bool, or null", self.inner.location))
+        }
+
+        fn parse_duration_unit(&mut self, number: i64) -> Result<TimeDelta, ParseError> {
+            let unit = self.parse_ident().unwrap();
+            let duration = match unit.as_str() {
+                "ms" => TimeDelta::try_milliseconds(number),
+                "s" => TimeDelta::try_seconds(number),
+                "m" => TimeDelta::try_minutes(number),
+                "h" => TimeDelta::try_hours(number),
+                "d" => TimeDelta::try_days(number),
+                _ => return Err(ParseError::new_spanned(
+                    "invalid duration unit, expected ms, s, m, h, or d",
+                    unit.location,
+                    unit.length,
+                )),
+            };
+            duration.ok_or_else(|| ParseError::new_spanned("duration out of range", unit.location, unit.length))
+        }
+    }
+}
+
 pub mod qql {
     use crate::db::parser::QQLParser;
     use crate::parser::{Ident, ParseError};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Statement {
         pub action: Action,
         pub quantifier: Quantifier,
         pub selectors: Vec<Selector>,
         pub where_clause: Option<WhereClause>,
+        pub group_by: Vec<GroupBy>,
+        pub having: Option<Expr>,
+        pub order_by: Vec<OrderBy>,
+        /// rows skipped before the quantifier applies
+        pub offset: Option<Expr>,
+        /// argument holding the order fields of the last row of the previous page
+        pub after: Option<Ident>,
     }
 
     impl<'a> QQLParser<'a> {
@@ -326,19 +602,43 @@ pub mod qql {
             let quantifier = self.parse_qql_quantifier()?;
             let selectors = self.parse_separated(',', Self::parse_qql_selector)?;
             let where_clause = self.parse_qql_where_clause()?;
+            let group_by = self.parse_qql_group_by()?;
+            let having = if self.take_keyword_insensitive("having") {
+                Some(self.parse_qql_expression()?)
+            } else {
+                None
+            };
+            let order_by = self.parse_qql_order_by()?;
+            let offset = if self.take_keyword_insensitive("offset") {
+                Some(self.parse_qql_expression()?)
+            } else {
+                None
+            };
+            let after = if self.take_keyword_insensitive("after") {
+                Some(self.parse_qql_interp()?
+                    .ok_or_else(|| ParseError::new("expected an argument after 'AFTER'", self.location))?)
+            } else {
+                None
+            };
 
             Ok(Statement {
                 action,
                 quantifier,
                 selectors,
                 where_clause,
+                group_by,
+                having,
+                order_by,
+                offset,
+                after,
             })
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum Action {
         Select,
+        Insert,
         Update,
         Delete,
     }
@@ -346,13 +646,14 @@ pub mod qql {
     impl<'a> QQLParser<'a> {
         pub fn parse_qql_action(&mut self) -> Result<Action, ParseError> {
             let ident = self.parse_ident()
-                .ok_or_else(|| ParseError::new("expected 'select' or 'update' or 'delete'", self.location))?;
+                .ok_or_else(|| ParseError::new("expected 'select', 'insert', 'update', or 'delete'", self.location))?;
             match ident.value.as_str() {
                 s if s.eq_ignore_ascii_case("select") => Ok(Action::Select),
+                s if s.eq_ignore_ascii_case("insert") => Ok(Action::Insert),
                 s if s.eq_ignore_ascii_case("update") => Ok(Action::Update),
                 s if s.eq_ignore_ascii_case("delete") => Ok(Action::Delete),
                 _ => Err(ParseError::new_spanned(
-                    "Expected 'select', 'update', or 'delete'",
+                    "Expected 'select', 'insert', 'update', or 'delete'",
                     ident.location,
                     ident.length,
                 ))
@@ -360,7 +661,7 @@ pub mod qql {
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub enum Quantifier {
         One,
         All,
@@ -391,10 +692,66 @@ pub mod qql {
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Selector {
         pub name: Ident,
-        pub fields: Vec<Ident>,
+        pub fields: Vec<SelectorField>,
+    }
+
+    /// `field` or `COUNT(field)` in a select, `field = expr` in an insert or update
+    #[derive(Debug, Clone)]
+    pub struct SelectorField {
+        pub name: Ident,
+        pub value: Option<Expr>,
+        pub aggregate: Option<Aggregate>,
+    }
+
+    impl SelectorField {
+        /// the name of the column the field is selected as
+        pub fn column(&self) -> String {
+            match self.aggregate {
+                Some(aggregate) => aggregate.column(&self.name),
+                None => self.name.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Aggregate {
+        Count,
+        Sum,
+        Avg,
+        Min,
+        Max,
+    }
+
+    impl Aggregate {
+        pub fn from_name(name: &str) -> Option<Self> {
+            Some(match name {
+                s if s.eq_ignore_ascii_case("count") => Aggregate::Count,
+                s if s.eq_ignore_ascii_case("sum") => Aggregate::Sum,
+                s if s.eq_ignore_ascii_case("avg") => Aggregate::Avg,
+                s if s.eq_ignore_ascii_case("min") => Aggregate::Min,
+                s if s.eq_ignore_ascii_case("max") => Aggregate::Max,
+                _ => return None,
+            })
+        }
+
+        /// the sql function, also used to name the column
+        pub fn function(&self) -> &'static str {
+            match self {
+                Aggregate::Count => "count",
+                Aggregate::Sum => "sum",
+                Aggregate::Avg => "avg",
+                Aggregate::Min => "min",
+                Aggregate::Max => "max",
+            }
+        }
+
+        /// `count_order_id` for `COUNT(order_id)`
+        pub fn column(&self, field: &Ident) -> String {
+            format!("{}_{}", self.function(), field)
+        }
     }
 
     impl<'a> QQLParser<'a> {
@@ -406,8 +763,20 @@ pub mod qql {
             let fields = self.parse_separated_terminated(
                 ')', ',',
                 |parser| {
-                    parser.parse_ident()
-                        .ok_or_else(|| ParseError::new("expected field name", parser.location))
+                    let name = parser.parse_ident()
+                        .ok_or_else(|| ParseError::new("expected field name", parser.location))?;
+                    if let Some(aggregate) = Aggregate::from_name(&name).filter(|_| parser.inner.take('(')) {
+                        let name = parser.parse_ident()
+                            .ok_or_else(|| ParseError::new("expected field name", parser.location))?;
+                        parser.inner.expect(')')?;
+                        return Ok(SelectorField { name, value: None, aggregate: Some(aggregate) });
+                    }
+                    let value = if parser.inner.take('=') {
+                        Some(parser.parse_qql_expression()?)
+                    } else {
+                        None
+                    };
+                    Ok(SelectorField { name, value, aggregate: None })
                 })?;
             self.inner.expect(')')?;
 
@@ -418,7 +787,7 @@ pub mod qql {
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct WhereClause {
         pub expr: Expr,
     }
@@ -437,13 +806,93 @@ pub mod qql {
         }
     }
 
-    #[derive(Debug)]
+    /// `field` or `Model.field`
+    #[derive(Debug, Clone)]
+    pub struct GroupBy {
+        pub model: Option<Ident>,
+        pub field: Ident,
+    }
+
+    impl<'a> QQLParser<'a> {
+        pub fn parse_qql_group_by(&mut self) -> Result<Vec<GroupBy>, ParseError> {
+            if !self.take_keyword_insensitive("group") {
+                return Ok(Vec::new());
+            }
+            if !self.take_keyword_insensitive("by") {
+                return Err(ParseError::new("expected 'BY' after 'GROUP'", self.location));
+            }
+
+            self.parse_separated(',', |parser| {
+                let (model, field) = parser.parse_qql_field_name()?;
+                Ok(GroupBy { model, field })
+            })
+        }
+
+        /// `field` or `Model.field`
+        fn parse_qql_field_name(&mut self) -> Result<(Option<Ident>, Ident), ParseError> {
+            let name = self.parse_ident()
+                .ok_or_else(|| ParseError::new("expected field name", self.location))?;
+            if !self.inner.take('.') {
+                return Ok((None, name));
+            }
+            let field = self.parse_ident()
+                .ok_or_else(|| ParseError::new("expected field name after model name", self.location))?;
+            Ok((Some(name), field))
+        }
+    }
+
+    /// `field`, `Model.field`, either followed by `ASC` or `DESC`
+    #[derive(Debug, Clone)]
+    pub struct OrderBy {
+        pub model: Option<Ident>,
+        pub field: Ident,
+        pub descending: bool,
+    }
+
+    impl<'a> QQLParser<'a> {
+        pub fn parse_qql_order_by(&mut self) -> Result<Vec<OrderBy>, ParseError> {
+            if !self.take_keyword_insensitive("order") {
+                return Ok(Vec::new());
+            }
+            if !self.take_keyword_insensitive("by") {
+                return Err(ParseError::new("expected 'BY' after 'ORDER'", self.location));
+            }
+
+            self.parse_separated(',', |parser| {
+                let (model, field) = parser.parse_qql_field_name()?;
+                let descending = if parser.take_keyword_insensitive("desc") {
+                    true
+                } else {
+                    parser.take_keyword_insensitive("asc");
+                    false
+                };
+                Ok(OrderBy { model, field, descending })
+            })
+        }
+    }
+
+    #[derive(Debug, Clone)]
     pub enum Expr {
         Binary(Box<Expr>, BinaryOp, Box<Expr>),
         Unary(UnaryOp, Box<Expr>),
         Number(u64),
+        String(String),
+        Bool(bool),
+        Null,
         Interp(Ident),
         Field(Option<Ident>, Ident),
+        /// `Enum.Variant`, parsed as a field and resolved during validation
+        Variant(Ident, Ident),
+        /// `COUNT(field)`, only in `HAVING`
+        Aggregate(Aggregate, Option<Ident>, Ident),
+        /// `expr IN (a, b)`
+        In(Box<Expr>, Vec<Expr>),
+        /// `expr IN #argument`, the argument is a list
+        InArgument(Box<Expr>, Ident),
+        /// `expr IS NULL`, or `expr IS NOT NULL` when negated
+        IsNull(Box<Expr>, bool),
+        /// `expr BETWEEN low AND high`, both ends included
+        Between(Box<Expr>, Box<Expr>, Box<Expr>),
         Group(Box<Expr>),
     }
 
@@ -488,18 +937,63 @@ pub mod qql {
         }
 
         pub fn parse_qql_expression_eq(&mut self) -> Result<Expr, ParseError> {
-            let mut expr = self.parse_qql_expression_ord()?;
+            let mut expr = self.parse_qql_expression_predicate()?;
             Ok(loop {
                 let op = match () {
                     _ if self.inner.take("==") => BinaryOp::Eq,
                     _ if self.inner.take("!=") => BinaryOp::Ne,
                     _ => break expr,
                 };
-                let rhs = self.parse_qql_expression_ord()?;
+                let rhs = self.parse_qql_expression_predicate()?;
                 expr = Expr::Binary(Box::new(expr), op, Box::new(rhs));
             })
         }
 
+        /// `IS [NOT] NULL`, `[NOT] LIKE`, `[NOT] IN` and `[NOT] BETWEEN`
+        pub fn parse_qql_expression_predicate(&mut self) -> Result<Expr, ParseError> {
+            let expr = self.parse_qql_expression_ord()?;
+
+            if self.take_keyword_insensitive("is") {
+                let negated = self.take_keyword_insensitive("not");
+                if !self.take_keyword_insensitive("null") {
+                    return Err(ParseError::new("expected 'NULL' after 'IS'", self.location));
+                }
+                return Ok(Expr::IsNull(Box::new(expr), negated));
+            }
+
+            let negated = self.take_keyword_insensitive("not");
+            let predicate = if self.take_keyword_insensitive("like") {
+                let pattern = self.parse_qql_expression_ord()?;
+                Expr::Binary(Box::new(expr), BinaryOp::Like, Box::new(pattern))
+            } else if self.take_keyword_insensitive("in") {
+                match self.parse_qql_interp()? {
+                    Some(arg) => Expr::InArgument(Box::new(expr), arg),
+                    None => {
+                        self.inner.expect('(')?;
+                        let values = self.parse_separated(',', Self::parse_qql_expression)?;
+                        self.inner.expect(')')?;
+                        Expr::In(Box::new(expr), values)
+                    }
+                }
+            } else if self.take_keyword_insensitive("between") {
+                let low = self.parse_qql_expression_ord()?;
+                if !self.take_keyword_insensitive("and") {
+                    return Err(ParseError::new("expected 'AND' in 'BETWEEN'", self.location));
+                }
+                let high = self.parse_qql_expression_ord()?;
+                Expr::Between(Box::new(expr), Box::new(low), Box::new(high))
+            } else if negated {
+                return Err(ParseError::new("expected 'LIKE', 'IN', or 'BETWEEN' after 'NOT'", self.location));
+            } else {
+                return Ok(expr);
+            };
+
+            Ok(match negated {
+                true => Expr::Unary(UnaryOp::Not, Box::new(predicate)),
+                false => predicate,
+            })
+        }
+
         pub fn parse_qql_expression_ord(&mut self) -> Result<Expr, ParseError> {
             let mut expr = self.parse_qql_expression_term()?;
             Ok(loop {
@@ -565,7 +1059,22 @@ pub mod qql {
                 return Ok(Expr::Number(number));
             }
 
+            if let Some(string) = self.parse_string()? {
+                return Ok(Expr::String(string));
+            }
+
             if let Some(ident) = self.parse_ident() {
+                match ident.as_str() {
+                    s if s.eq_ignore_ascii_case("null") => return Ok(Expr::Null),
+                    s if s.eq_ignore_ascii_case("true") => return Ok(Expr::Bool(true)),
+                    s if s.eq_ignore_ascii_case("false") => return Ok(Expr::Bool(false)),
+                    _ => {}
+                }
+                if let Some(aggregate) = Aggregate::from_name(&ident).filter(|_| self.inner.take('(')) {
+                    let (model, field) = self.parse_qql_field_name()?;
+                    self.inner.expect(')')?;
+                    return Ok(Expr::Aggregate(aggregate, model, field));
+                }
                 return if self.inner.take('.') {
                     let field_name = self.parse_ident()
                         .ok_or_else(|| ParseError::new("expected field name after model name", self.location))?;
@@ -575,14 +1084,7 @@ pub mod qql {
                 };
             }
 
-            if let Some(ident) = self.atomic(|parser| {
-                if !parser.inner.take('#') {
-                    return Ok(None);
-                }
-                let ident = parser.parse_ident()
-                    .ok_or_else(|| ParseError::new("expected argument name", parser.location))?;
-                Ok(Some(ident))
-            })? {
+            if let Some(ident) = self.parse_qql_interp()? {
                 return Ok(Expr::Interp(ident));
             }
 
@@ -594,9 +1096,22 @@ pub mod qql {
 
             Err(ParseError::new("expected primary expression", self.location))
         }
+
+        /// `#argument`
+        pub fn parse_qql_interp(&mut self) -> Result<Option<Ident>, ParseError> {
+            self.inner.whitespace();
+            self.atomic(|parser| {
+                if !parser.inner.take('#') {
+                    return Ok(None);
+                }
+                let ident = parser.parse_ident()
+                    .ok_or_else(|| ParseError::new("expected argument name", parser.location))?;
+                Ok(Some(ident))
+            })
+        }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub enum BinaryOp {
         Mul,
         Div,
@@ -609,11 +1124,13 @@ pub mod qql {
         Ge,
         Eq,
         Ne,
+        /// `%` matches any run of characters, `_` any one, `\` escapes either
+        Like,
         And,
         Or,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub enum UnaryOp {
         Not,
         Negative,
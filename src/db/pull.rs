@@ -0,0 +1,85 @@
+use std::fmt::Write;
+use anyhow::bail;
+use crate::db::backend::{ColumnSchema, TableSchema};
+use crate::db::Context;
+
+pub async fn pull(context: &Context, force: bool) -> anyhow::Result<()> {
+    let tables = context.driver.introspect().await?;
+
+    // every file is checked and generated before the first is written
+    let mut files = Vec::new();
+    for table in &tables {
+        if !is_ident(&table.name) {
+            eprintln!("skipping table {:?}: name is not a valid model name", table.name);
+            continue;
+        }
+
+        let file_path = context.path.join(format!("{}.qql", table.name.to_lowercase()));
+        if file_path.exists() && !force {
+            bail!("{} already exists, use --force to overwrite it", file_path.display());
+        }
+        if files.iter().any(|(path, _)| *path == file_path) {
+            bail!("more than one table would be written to {}", file_path.display());
+        }
+
+        files.push((file_path, model_source(context, table)?));
+    }
+
+    for (file_path, source) in files {
+        std::fs::write(&file_path, source)?;
+        println!("wrote {}", file_path.display());
+    }
+
+    Ok(())
+}
+
+fn model_source(context: &Context, table: &TableSchema) -> anyhow::Result<String> {
+    let mut fields = Vec::new();
+    for column in &table.columns {
+        if !is_ident(&column.name) {
+            eprintln!("skipping {}.{}: name is not a valid field name", table.name, column.name);
+            continue;
+        }
+
+        match field_type(context, column) {
+            Some(type_) => fields.push(format!("{}: {}", column.name, type_)),
+            None => eprintln!(
+                "skipping {}.{}: unsupported column type {:?}",
+                table.name, column.name, column.sql_type,
+            ),
+        }
+    }
+
+    let mut builder = String::new();
+    write!(&mut builder, "model {} {{", table.name)?;
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(&mut builder, ",")?;
+        }
+        write!(&mut builder, "\n    {field}")?;
+    }
+    writeln!(&mut builder, "\n}}")?;
+
+    Ok(builder)
+}
+
+fn field_type(context: &Context, column: &ColumnSchema) -> Option<String> {
+    let name = column.data_type?.name();
+    context.type_store.get(name)?;
+
+    let mut out = name.to_owned();
+    if !column.args.is_empty() {
+        let args = column.args.iter().map(u64::to_string).collect::<Vec<_>>();
+        write!(&mut out, "({})", args.join(", ")).unwrap();
+    }
+    if column.optional {
+        out.push('?');
+    }
+    Some(out)
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
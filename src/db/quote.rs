@@ -0,0 +1,259 @@
+use std::fmt::{Display, Formatter, Write};
+
+/// A table, column, index or type name as sql reads it: in double quotes, with embedded double
+/// quotes doubled. Every generated statement quotes names this way, so keywords like `user`,
+/// mixed case and any unicode are kept exactly as the model writes them.
+///
+/// Rust's `{:?}` looks similar but escapes backslashes, control characters and quotes with
+/// backslashes, which sql does not understand.
+#[derive(Debug, Clone, Copy)]
+pub struct Quoted<'a>(pub &'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            if c == '"' {
+                f.write_char('"')?;
+            }
+            f.write_char(c)?;
+        }
+        f.write_char('"')
+    }
+}
+
+/// `Model.field` with both names quoted.
+#[derive(Debug, Clone, Copy)]
+pub struct Qualified<'a>(pub &'a str, pub &'a str);
+
+impl Display for Qualified<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", Quoted(self.0), Quoted(self.1))
+    }
+}
+
+/// A string literal, in single quotes with embedded single quotes doubled. Only used in
+/// statements that cannot take parameters, like enum definitions.
+#[derive(Debug, Clone, Copy)]
+pub struct Literal<'a>(pub &'a str);
+
+impl Display for Literal<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_char('\'')?;
+        for c in self.0.chars() {
+            if c == '\'' {
+                f.write_char('\'')?;
+            }
+            f.write_char(c)?;
+        }
+        f.write_char('\'')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use hashbrown::HashMap;
+    use crate::db::ast::{Index, Model, ModelField, Query};
+    use crate::db::backend::{ConnectOptions, Driver, PoolOptions, PostgresDriver, SqliteDriver};
+    use crate::db::log::QueryLog;
+    use crate::db::parser::qql::{Expr, Statement};
+    use crate::db::seed::build_query;
+    use crate::db::types::{BytesType, IntType, StringType, Type};
+    use crate::db::value::Value;
+    use crate::parser::{Ident, Location};
+    use super::*;
+
+    const TABLE: &str = "order \"items\" \\ 表";
+    const KEY: &str = "select";
+    const LABEL: &str = "Label \"quoted\"";
+    const NOTE: &str = "back\\slash ü";
+    const DATA: &str = "blob) or (1";
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(Quoted("user").to_string(), r#""user""#);
+        assert_eq!(Quoted("userName").to_string(), r#""userName""#);
+        assert_eq!(Quoted(r#"say "hi""#).to_string(), r#""say ""hi""""#);
+        assert_eq!(Quoted(r"back\slash").to_string(), r#""back\slash""#);
+        assert_eq!(Quoted("naïve 名前").to_string(), "\"naïve 名前\"");
+        assert_eq!(Quoted("tab\there").to_string(), "\"tab\there\"");
+        assert_eq!(Qualified("User", "select").to_string(), r#""User"."select""#);
+        assert_eq!(Literal("it's").to_string(), "'it''s'");
+    }
+
+    #[test]
+    fn index_names_are_quoted() {
+        let model = Model {
+            name: ident(TABLE),
+            fields: vec![field(KEY, Arc::new(IntType), Vec::new(), false, Some(Index::Unique))],
+        };
+        assert_eq!(
+            Index::Unique.create_statement(&model, &model.fields[0]).unwrap(),
+            r#"create unique index if not exists "order ""items"" \ 表_select_5a1c129e_key" on "order ""items"" \ 表" ("select")"#,
+        );
+    }
+
+    #[test]
+    fn index_names_are_distinct_and_fit_postgres() {
+        let name = |model: &str, field_name: &str| {
+            let model = Model { name: ident(model), fields: Vec::new() };
+            Index::name(&model, &field(field_name, Arc::new(IntType), Vec::new(), false, None), "key")
+        };
+        assert_ne!(name("A_b", "c"), name("A", "b_c"));
+
+        let long = "表".repeat(30);
+        let first = name(&long, "first");
+        let second = name(&long, "second");
+        assert_ne!(first, second);
+        for name in [&first, &second] {
+            assert!(name.len() <= 63, "{name} is {} bytes", name.len());
+            assert!(name.starts_with("表表表") && name.ends_with("_key"));
+        }
+    }
+
+    #[tokio::test]
+    async fn bytes_checks_are_quoted() {
+        let connect = ConnectOptions {
+            username: Some("example".to_owned()),
+            database: Some("example".to_owned()),
+            ..ConnectOptions::default()
+        };
+        // the pool connects lazily, nothing here reaches a server
+        let driver = PostgresDriver::new(connect, PoolOptions::default(), Arc::new(QueryLog::default())).unwrap();
+        let data = field(DATA, Arc::new(BytesType), vec![4], true, None);
+        assert_eq!(
+            driver.type_definition(&data).unwrap(),
+            r#"bytea CHECK (octet_length("blob) or (1") <= 4)"#,
+        );
+    }
+
+    #[tokio::test]
+    async fn hostile_names_round_trip_through_sqlite() {
+        let model = Model {
+            name: ident(TABLE),
+            fields: vec![
+                field(KEY, Arc::new(IntType), Vec::new(), false, Some(Index::Unique)),
+                field(LABEL, Arc::new(StringType), vec![16], false, Some(Index::Plain)),
+                field(NOTE, Arc::new(StringType), Vec::new(), true, None),
+                field(DATA, Arc::new(BytesType), vec![4], true, None),
+            ],
+        };
+        let models = HashMap::from([(model.name.clone(), model.clone())]);
+        let driver = SqliteDriver::new(PathBuf::from(":memory:"), Arc::new(QueryLog::default())).unwrap();
+        driver.migrate_up(&model).await.unwrap();
+        // finds the table and indexes it created
+        driver.migrate_up(&model).await.unwrap();
+
+        let tables = driver.introspect().await.unwrap();
+        let columns = tables.iter()
+            .find(|table| table.name == TABLE).unwrap()
+            .columns.iter()
+            .map(|column| column.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(columns, [KEY, LABEL, NOTE, DATA]);
+
+        let insert = query("query Insert(k, l, n) { INSERT ONE T(k = #k, l = #l, n = #n) }");
+        for key in [1, 2] {
+            let args = [Value::Int(key), Value::String(format!("label {key}")), Value::String(format!("it's \"{key}\""))];
+            driver.execute(&models, &insert, &args).await.unwrap();
+        }
+        // the unique index and the length check are on the right columns
+        let duplicate = [Value::Int(1), Value::String("other".to_owned()), Value::Null];
+        assert!(driver.execute(&models, &insert, &duplicate).await.is_err());
+        let long = [Value::Int(3), Value::String("x".repeat(17)), Value::Null];
+        assert!(driver.execute(&models, &insert, &long).await.is_err());
+        // negative keys stay out of the selects below
+        let data = query("query Data(k, d) { INSERT ONE T(k = #k, l = \"data\", d = #d) }");
+        driver.execute(&models, &data, &[Value::Int(-1), Value::Bytes(vec![1, 2, 3, 4])]).await.unwrap();
+        assert!(driver.execute(&models, &data, &[Value::Int(-2), Value::Bytes(vec![0; 5])]).await.is_err());
+
+        let update = query("query Rename(k, l) { UPDATE ALL T(l = #l) WHERE k == #k }");
+        driver.execute(&models, &update, &[Value::Int(2), Value::String("deux".to_owned())]).await.unwrap();
+
+        let select = query("query List(k) { SELECT ALL T(k, l, n) WHERE k >= #k ORDER BY k DESC }");
+        let result = driver.execute(&models, &select, &[Value::Int(1)]).await.unwrap();
+        assert_eq!(result.rows, [
+            vec![Value::Int(2), Value::String("deux".to_owned()), Value::String("it's \"2\"".to_owned())],
+            vec![Value::Int(1), Value::String("label 1".to_owned()), Value::String("it's \"1\"".to_owned())],
+        ]);
+
+        let delete = query("query Remove(k) { DELETE ALL T() WHERE k == #k }");
+        driver.execute(&models, &delete, &[Value::Int(1)]).await.unwrap();
+        assert_eq!(driver.execute(&models, &select, &[Value::Int(0)]).await.unwrap().rows.len(), 1);
+
+        driver.migrate_down(&model).await.unwrap();
+        assert!(driver.introspect().await.unwrap().iter().all(|table| table.name != TABLE));
+    }
+
+    fn ident(value: &str) -> Ident {
+        Ident {
+            value: value.to_owned(),
+            location: Location { index: 0, line: 1, column: 1 },
+            length: value.len(),
+        }
+    }
+
+    fn field(name: &str, repr: Arc<dyn Type>, args: Vec<u64>, optional: bool, index: Option<Index>) -> ModelField {
+        ModelField {
+            name: ident(name),
+            type_name: ident(repr.data_type().name()),
+            repr,
+            optional,
+            args,
+            default: None,
+            timestamp: None,
+            index,
+            rules: Vec::new(),
+        }
+    }
+
+    /// QQL cannot spell the names, so queries are written with `T`, `k`, `l`, `n` and `d` instead.
+    fn query(source: &str) -> Query {
+        fn rename(name: &mut Ident) {
+            let hostile = match name.as_str() {
+                "T" => TABLE,
+                "k" => KEY,
+                "l" => LABEL,
+                "n" => NOTE,
+                "d" => DATA,
+                _ => return,
+            };
+            *name = ident(hostile);
+        }
+        fn rename_expr(expr: &mut Expr) {
+            match expr {
+                Expr::Field(model, field) => {
+                    if let Some(model) = model {
+                        rename(model);
+                    }
+                    rename(field);
+                }
+                Expr::Binary(lhs, _, rhs) => {
+                    rename_expr(lhs);
+                    rename_expr(rhs);
+                }
+                _ => {}
+            }
+        }
+        fn rename_statement(statement: &mut Statement) {
+            for selector in &mut statement.selectors {
+                rename(&mut selector.name);
+                for field in &mut selector.fields {
+                    rename(&mut field.name);
+                }
+            }
+            if let Some(where_clause) = &mut statement.where_clause {
+                rename_expr(&mut where_clause.expr);
+            }
+            for order in &mut statement.order_by {
+                rename(&mut order.field);
+            }
+        }
+
+        let mut query = build_query(source).unwrap();
+        rename_statement(&mut query.statement);
+        query
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::{Display, Formatter};
+use hashbrown::HashMap;
+use regex::Regex;
+use rust_decimal::Decimal;
+use thiserror::Error;
+use crate::db::ast::{Model, Query};
+use crate::db::parser::qql::{Action, Expr};
+use crate::db::types::DataType;
+use crate::db::value::Value;
+use crate::parser::Ident;
+
+/// A constraint written as a field attribute, `@min(3)`, checked in rust before a query writes
+/// the field.
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// the minimum length of a string, or the minimum of a number
+    Min(i64),
+    /// the maximum length of a string, or the maximum of a number
+    Max(i64),
+    /// the whole string must match `regex`, which is built from `source`
+    Pattern {
+        source: String,
+        regex: Regex,
+    },
+    Email,
+}
+
+impl Rule {
+    /// The rule for the attribute `@name(args)` on a field of `data_type`, `Ok(None)` when the
+    /// attribute is not a rule.
+    pub fn from_attribute(name: &str, args: &[Value], data_type: DataType) -> Result<Option<Self>, String> {
+        let numeric = matches!(
+            data_type,
+            DataType::Int | DataType::BigInt | DataType::Decimal | DataType::Float,
+        );
+        let rule = match (name, args) {
+            ("min" | "max", _) if data_type != DataType::String && !numeric => {
+                return Err("only strings and numbers have a minimum or maximum".to_owned());
+            }
+            ("min", [Value::Int(min)]) => Rule::Min(*min),
+            ("max", [Value::Int(max)]) => Rule::Max(*max),
+            ("min" | "max", _) => return Err("takes one integer argument".to_owned()),
+            ("pattern" | "email", _) if data_type != DataType::String => {
+                return Err("only strings can be matched".to_owned());
+            }
+            ("pattern", [Value::String(source)]) => Rule::Pattern {
+                source: source.clone(),
+                regex: Regex::new(&format!("^(?:{source})$")).map_err(|e| e.to_string())?,
+            },
+            ("pattern", _) => return Err("takes one string argument, the pattern".to_owned()),
+            ("email", []) => Rule::Email,
+            ("email", _) => return Err("takes no arguments".to_owned()),
+            _ => return Ok(None),
+        };
+        Ok(Some(rule))
+    }
+
+    /// Checks a value already converted to the field's type, nulls are not checked.
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let valid = match (self, value) {
+            (Rule::Min(min), Value::String(value)) => value.chars().count() as i64 >= *min,
+            (Rule::Max(max), Value::String(value)) => value.chars().count() as i64 <= *max,
+            (Rule::Min(min), Value::Int(value)) => value >= min,
+            (Rule::Max(max), Value::Int(value)) => value <= max,
+            (Rule::Min(min), Value::Decimal(value)) => *value >= Decimal::from(*min),
+            (Rule::Max(max), Value::Decimal(value)) => *value <= Decimal::from(*max),
+            (Rule::Min(min), Value::Float(value)) => *value >= *min as f64,
+            (Rule::Max(max), Value::Float(value)) => *value <= *max as f64,
+            (Rule::Pattern { regex, .. }, Value::String(value)) => regex.is_match(value),
+            (Rule::Email, Value::String(value)) => is_email(value),
+            _ => true,
+        };
+        if valid {
+            return Ok(());
+        }
+
+        Err(match (self, value) {
+            (Rule::Min(min), Value::String(_)) => format!("must be at least {min} characters"),
+            (Rule::Max(max), Value::String(_)) => format!("must be at most {max} characters"),
+            (Rule::Min(min), _) => format!("must be at least {min}"),
+            (Rule::Max(max), _) => format!("must be at most {max}"),
+            (Rule::Pattern { source, .. }, _) => format!("must match {source}"),
+            (Rule::Email, _) => "must be an email address".to_owned(),
+        })
+    }
+}
+
+impl Display for Rule {
+    /// the attribute the rule was written as
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::Min(min) => write!(f, "@min({min})"),
+            Rule::Max(max) => write!(f, "@max({max})"),
+            Rule::Pattern { source, .. } => write!(f, "@pattern({source:?})"),
+            Rule::Email => write!(f, "@email"),
+        }
+    }
+}
+
+/// `local@domain.tld` without whitespace, deliverability is left to the application
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.contains(char::is_whitespace)
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+/// Every field of one write that broke a rule.
+#[derive(Error, Debug, Clone)]
+pub struct FieldErrors {
+    pub model: Ident,
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldError {
+    pub field: Ident,
+    pub message: String,
+}
+
+impl Display for FieldErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid {}", self.model)?;
+        for (i, error) in self.errors.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            write!(f, "{separator}{} {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the arguments an insert or update writes against the rules of their fields, so no
+/// driver runs a write with invalid values.
+pub fn check_query(models: &HashMap<Ident, Model>, query: &Query, args: &[Value]) -> Result<(), FieldErrors> {
+    let statement = &query.statement;
+    if !matches!(statement.action, Action::Insert | Action::Update) {
+        return Ok(());
+    }
+
+    for selector in &statement.selectors {
+        let Some(model) = models.get(&selector.name) else {
+            continue;
+        };
+
+        let mut errors = Vec::new();
+        for field in &selector.fields {
+            let (Some(Expr::Interp(arg)), Some(model_field)) = (&field.value, model.get_field(&field.name)) else {
+                continue;
+            };
+            let Some(value) = query.args.iter().position(|a| a == arg).and_then(|i| args.get(i)) else {
+                continue;
+            };
+            // values that do not convert are reported by the driver
+            let Ok(value) = value.coerce(model_field.repr.data_type()) else {
+                continue;
+            };
+            if value.is_null() {
+                continue;
+            }
+
+            let failed = model_field.rules.iter().find_map(|rule| rule.check(&value).err());
+            if let Some(message) = failed {
+                errors.push(FieldError {
+                    field: field.name.clone(),
+                    message,
+                });
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(FieldErrors {
+                model: model.name.clone(),
+                errors,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context as _};
+use hashbrown::HashMap;
+use serde_derive::Deserialize;
+use crate::db::Context;
+use crate::db::ast::{Model, Query};
+use crate::db::compile::json_value;
+use crate::db::parser::QQLFile;
+use crate::db::types::DataType;
+use crate::db::validate::read_directory;
+use crate::db::value::Value;
+use crate::parser::Ident;
+
+/// A seed file, `users.ron` or `users.json`, placed next to the qql files:
+///
+/// ```ron
+/// (
+///     model: "User",
+///     key: ["username"],
+///     rows: [
+///         { "username": "ann", "email": "ann@example.com", "password": "hunter22" },
+///     ],
+/// )
+/// ```
+///
+/// Rows are inserted the way the model's queries insert them, so values are hashed, encrypted
+/// and defaulted the same. A row whose `key` fields match a stored row is skipped.
+#[derive(Deserialize, Debug)]
+struct SeedFile {
+    model: String,
+    key: Vec<String>,
+    rows: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// The rows of one seed file, checked against its model. Its name is the file's path like a
+/// query namespace, `billing/invoices.ron` is `billing.invoices`.
+#[derive(Debug, Clone)]
+pub struct FixtureSet {
+    pub name: String,
+    pub path: PathBuf,
+    pub model: Ident,
+    pub key: Vec<Ident>,
+    /// the fields each row sets, converted to their types
+    pub rows: Vec<Vec<(Ident, Value)>>,
+}
+
+/// What seeding one set did, rows already stored are skipped.
+#[derive(Debug, Clone)]
+pub struct Seeded {
+    pub name: String,
+    pub model: Ident,
+    pub inserted: usize,
+    pub skipped: usize,
+}
+
+/// Inserts the named fixture sets, or every set when `names` is empty, in one transaction.
+/// Every set is checked before anything is written.
+pub async fn seed(context: &Context, names: &[impl AsRef<str>]) -> anyhow::Result<Vec<Seeded>> {
+    let models = context.models();
+    let mut sets = read_fixture_sets(context)?;
+    if !names.is_empty() {
+        for name in names {
+            if !sets.iter().any(|set| set.name == name.as_ref()) {
+                bail!("no seed file named {:?} in {}", name.as_ref(), context.path.display());
+            }
+        }
+        sets.retain(|set| names.iter().any(|name| set.name == name.as_ref()));
+    }
+
+    let mut transaction = context.begin().await?;
+    let mut out = Vec::new();
+    for set in &sets {
+        let model = &models[&set.model];
+        let mut seeded = Seeded {
+            name: set.name.clone(),
+            model: set.model.clone(),
+            inserted: 0,
+            skipped: 0,
+        };
+        for row in &set.rows {
+            let key = set.key.iter()
+                .map(|key| row.iter().find(|(field, _)| field == key).unwrap().clone())
+                .collect::<Vec<_>>();
+            let find = find_query(model, &key)?;
+            let args = key.into_iter().map(|(_, value)| value).collect::<Vec<_>>();
+            let found = transaction.execute(&models, &find, &args).await
+                .with_context(|| format!("unable to seed {}", set.path.display()))?;
+            if !found.rows.is_empty() {
+                seeded.skipped += 1;
+                continue;
+            }
+
+            let insert = insert_query(model, row)?;
+            let args = row.iter().map(|(_, value)| value.clone()).collect::<Vec<_>>();
+            transaction.execute(&models, &insert, &args).await
+                .with_context(|| format!("unable to seed {}", set.path.display()))?;
+            seeded.inserted += 1;
+        }
+        out.push(seeded);
+    }
+    transaction.commit().await?;
+    Ok(out)
+}
+
+/// Reads and checks every seed file below the database directory, models must already be
+/// validated.
+pub fn read_fixture_sets(context: &Context) -> anyhow::Result<Vec<FixtureSet>> {
+    let mut files = Vec::new();
+    read_directory(&context.path, &["ron", "json"], "", &mut files)?;
+
+    let models = context.models();
+    let mut sets = Vec::new();
+    let mut errors = Vec::new();
+    for (name, path) in files {
+        let content = std::fs::read_to_string(&path)?;
+        let file: SeedFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&content)
+                .with_context(|| format!("unable to parse {}", path.display()))?,
+            _ => ron::from_str(&content)
+                .with_context(|| format!("unable to parse {}", path.display()))?,
+        };
+        sets.extend(fixture_set(&models, name, &path, file, &mut errors));
+    }
+    if !errors.is_empty() {
+        bail!("invalid seed data:\n{}", errors.join("\n"));
+    }
+    Ok(sets)
+}
+
+fn fixture_set(
+    models: &HashMap<Ident, Model>,
+    name: String,
+    path: &Path,
+    file: SeedFile,
+    errors: &mut Vec<String>,
+) -> Option<FixtureSet> {
+    let path_display = path.display();
+    let Some(model) = models.get(file.model.as_str()) else {
+        errors.push(format!("{path_display}: no model named {}", file.model));
+        return None;
+    };
+    if file.key.is_empty() {
+        errors.push(format!("{path_display}: key must name at least one field of {}", model.name));
+        return None;
+    }
+    let mut key = Vec::new();
+    for name in &file.key {
+        match model.get_field(name) {
+            Some(field) => key.push(field.name.clone()),
+            None => {
+                errors.push(format!("{path_display}: key: {} has no field {name}", model.name));
+                return None;
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (i, row) in file.rows.iter().enumerate() {
+        let row = row.iter()
+            .map(|(name, value)| (name.clone(), json_field_value(model, name, value)))
+            .collect();
+        match check_row(model, row, &key) {
+            Ok(values) => rows.push(values),
+            Err(messages) => errors.extend(messages.into_iter()
+                .map(|message| format!("{path_display}: row {}: {message}", i + 1))),
+        }
+    }
+
+    Some(FixtureSet {
+        name,
+        path: path.to_path_buf(),
+        model: model.name.clone(),
+        key,
+        rows,
+    })
+}
+
+/// A value read from JSON or RON for the field `name`, JSON fields keep the value as written
+/// instead of parsing strings.
+pub(crate) fn json_field_value(model: &Model, name: &str, value: &serde_json::Value) -> Value {
+    match model.get_field(name) {
+        Some(field) if field.repr.data_type() == DataType::Json && !value.is_null() => Value::Json(value.clone()),
+        _ => json_value(value),
+    }
+}
+
+/// Converts the fields a row sets to their types and checks them like an insert would, every
+/// problem is returned. `key` fields must be set and not null.
+pub(crate) fn check_row(model: &Model, row: Vec<(String, Value)>, key: &[Ident]) -> Result<Vec<(Ident, Value)>, Vec<String>> {
+    let mut errors = Vec::new();
+    for (name, _) in &row {
+        if !model.has_field(name) {
+            errors.push(format!("{} has no field {name}", model.name));
+        }
+    }
+
+    let mut values = Vec::new();
+    for field in &model.fields {
+        let value = match row.iter().find(|(name, _)| field.name == *name) {
+            Some((_, value)) => value,
+            None if key.contains(&field.name) => {
+                errors.push(format!("{}.{} is part of the key and must be set", model.name, field.name));
+                continue;
+            }
+            None => {
+                let generated = field.optional
+                    || field.default.is_some()
+                    || field.timestamp.is_some()
+                    || field.repr.data_type() == DataType::UUID;
+                if !generated {
+                    errors.push(format!("{}.{} is required", model.name, field.name));
+                }
+                continue;
+            }
+        };
+
+        let value = match value.coerce(field.repr.data_type()) {
+            Ok(value) => value.into_owned(),
+            Err(e) => {
+                errors.push(format!("{}.{}: {e}", model.name, field.name));
+                continue;
+            }
+        };
+        if value.is_null() {
+            if !field.optional || key.contains(&field.name) {
+                errors.push(format!("{}.{} must not be null", model.name, field.name));
+            }
+            values.push((field.name.clone(), value));
+            continue;
+        }
+
+        let failed = field.repr.validate(&value).err()
+            .or_else(|| field.rules.iter().find_map(|rule| rule.check(&value).err()));
+        match failed {
+            Some(message) => errors.push(format!("{}.{} {message}", model.name, field.name)),
+            None => values.push((field.name.clone(), value)),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(values),
+        false => Err(errors),
+    }
+}
+
+/// `SELECT ONE Model(key) WHERE key == #key`
+fn find_query(model: &Model, key: &[(Ident, Value)]) -> anyhow::Result<Query> {
+    let names = key.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    let conditions = names.iter().map(|name| format!("{name} == #{name}")).collect::<Vec<_>>();
+    build_query(&format!(
+        "query Seed({names}) {{ SELECT ONE {}({names}) WHERE {} }}",
+        model.name,
+        conditions.join(" AND "),
+        names = names.join(", "),
+    ))
+}
+
+/// `INSERT ONE Model(field = #field)`
+pub(crate) fn insert_query(model: &Model, row: &[(Ident, Value)]) -> anyhow::Result<Query> {
+    let names = row.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    let values = names.iter().map(|name| format!("{name} = #{name}")).collect::<Vec<_>>();
+    build_query(&format!(
+        "query Seed({}) {{ INSERT ONE {}({}) }}",
+        names.join(", "),
+        model.name,
+        values.join(", "),
+    ))
+}
+
+/// A query that is run without being registered, its fields were checked against the model.
+pub(crate) fn build_query(source: &str) -> anyhow::Result<Query> {
+    let file: QQLFile = source.parse()
+        .with_context(|| format!("unable to parse the generated query {source}"))?;
+    let query = file.queries.into_values().next()
+        .ok_or_else(|| anyhow!("unable to parse the generated query {source}"))?;
+    Ok(Query {
+        name: query.name,
+        args: query.args,
+        statement: query.statement,
+        cache: None,
+    })
+}
@@ -0,0 +1,315 @@
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+use anyhow::{anyhow, bail, Context as _};
+use chrono::TimeDelta;
+use hashbrown::HashMap;
+use crate::db::Context;
+use crate::db::ast::{Model, Query};
+use crate::db::seed::{build_query, check_row, insert_query, json_field_value};
+use crate::db::types::{DataType, Type};
+use crate::db::value::Value;
+use crate::parser::Ident;
+
+/// How rows are written by `export` and read by `import`. JSON and RON write one object per
+/// line, so files are read and written a row at a time.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// a header with the field names, then a line per row; empty unquoted cells are null
+    Csv,
+    Json,
+    Ron,
+}
+
+impl Format {
+    /// The format a file name ends in, `users.csv`.
+    pub fn from_extension(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "json" | "jsonl" => Some(Format::Json),
+            "ron" => Some(Format::Ron),
+            _ => None,
+        }
+    }
+}
+
+/// Writes every row of `model` with all of its fields, streaming them from the driver. Values
+/// are decoded like any read, so `Encrypted` fields are written in plaintext, while `Hashed`
+/// fields keep their hashes.
+pub async fn export(context: &Context, model: &str, format: Format, out: &mut (dyn Write + Send)) -> anyhow::Result<u64> {
+    let models = context.models();
+    let model = models.get(model).ok_or_else(|| anyhow!("no model named {model}"))?;
+    let names = model.fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>();
+    let query = build_query(&format!("query Export() {{ SELECT ALL {}({}) }}", model.name, names.join(", ")))?;
+
+    if format == Format::Csv {
+        let header = names.iter().map(|name| csv_cell(&Value::String(name.to_string()))).collect::<Vec<_>>();
+        writeln!(out, "{}", header.join(","))?;
+    }
+    let mut count = 0;
+    context.driver.stream(&models, &query, &[], &mut |row| {
+        match format {
+            Format::Csv => {
+                let cells = row.iter().map(csv_cell).collect::<Vec<_>>();
+                writeln!(out, "{}", cells.join(","))?;
+            }
+            Format::Json | Format::Ron => {
+                let object = names.iter()
+                    .zip(row)
+                    .map(|(name, value)| (name.to_string(), export_value(value)))
+                    .collect::<serde_json::Map<_, _>>();
+                let object = serde_json::Value::Object(object);
+                match format {
+                    Format::Json => writeln!(out, "{}", serde_json::to_string(&object)?)?,
+                    _ => writeln!(out, "{}", ron::to_string(&object)?)?,
+                }
+            }
+        }
+        count += 1;
+        Ok(())
+    }).await?;
+    out.flush()?;
+    Ok(count)
+}
+
+/// Inserts the rows of `input` into `model` in one transaction. Rows that do not convert to the
+/// model's fields are collected with the line they start on, and nothing is inserted when there
+/// are any.
+pub async fn import(context: &Context, model: &str, format: Format, input: impl BufRead) -> anyhow::Result<u64> {
+    let models = context.models();
+    let model = models.get(model).ok_or_else(|| anyhow!("no model named {model}"))?;
+    // exported hashes are written as they are, encoding them would hash the hash, and the
+    // rules were checked against the value that was hashed
+    let mut stored = model.clone();
+    for field in &mut stored.fields {
+        if field.repr.one_way() {
+            field.repr = Arc::new(Encoded(field.repr.clone()));
+            field.rules.clear();
+        }
+    }
+    let mut insert_models = (*models).clone();
+    insert_models.insert(stored.name.clone(), stored.clone());
+
+    let mut transaction = context.begin().await?;
+    // rows setting the same fields share their insert
+    let mut queries = HashMap::<Vec<Ident>, Query>::new();
+    let mut errors = Vec::new();
+    let mut count = 0;
+    let mut records = Records::new(format, input);
+    while let Some((line, record)) = records.next(model)? {
+        let row = match record.map_err(|e| vec![e]).and_then(|row| check_row(&stored, row, &[])) {
+            Ok(row) => row,
+            Err(messages) => {
+                errors.extend(messages.into_iter().map(|message| format!("line {line}: {message}")));
+                continue;
+            }
+        };
+        if !errors.is_empty() {
+            // nothing is inserted, the remaining rows are only checked
+            continue;
+        }
+
+        let fields = row.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        if !queries.contains_key(&fields) {
+            queries.insert(fields.clone(), insert_query(model, &row)?);
+        }
+        let query = &queries[&fields];
+        let args = row.into_iter().map(|(_, value)| value).collect::<Vec<_>>();
+        transaction.execute(&insert_models, query, &args).await
+            .with_context(|| format!("unable to import line {line}"))?;
+        count += 1;
+    }
+
+    if !errors.is_empty() {
+        transaction.rollback().await?;
+        bail!("unable to import {}, no rows were inserted:\n{}", model.name, errors.join("\n"));
+    }
+    transaction.commit().await?;
+    Ok(count)
+}
+
+/// A one-way type whose values are already encoded, see `Type::one_way`.
+#[derive(Debug)]
+struct Encoded(Arc<dyn Type>);
+impl Type for Encoded {
+    fn data_type(&self) -> DataType {
+        self.0.data_type()
+    }
+}
+
+/// Reads the rows of an export, each with the line it starts on.
+struct Records<R> {
+    format: Format,
+    lines: std::io::Lines<R>,
+    line: usize,
+    /// the field names of a csv header
+    header: Option<Vec<String>>,
+}
+
+type Record = Result<Vec<(String, Value)>, String>;
+
+impl<R: BufRead> Records<R> {
+    fn new(format: Format, input: R) -> Self {
+        Records {
+            format,
+            lines: input.lines(),
+            line: 0,
+            header: None,
+        }
+    }
+
+    fn next_line(&mut self) -> anyhow::Result<Option<String>> {
+        match self.lines.next() {
+            Some(line) => {
+                self.line += 1;
+                Ok(Some(line?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next(&mut self, model: &Model) -> anyhow::Result<Option<(usize, Record)>> {
+        loop {
+            let Some(line) = self.next_line()? else {
+                return Ok(None);
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let start = self.line;
+
+            let object = match self.format {
+                Format::Csv => {
+                    let cells = self.csv_record(line)?;
+                    let Some(header) = &self.header else {
+                        let header = cells.into_iter()
+                            .map(|cell| cell.ok_or_else(|| anyhow!("line {start}: the header names every column")))
+                            .collect::<anyhow::Result<Vec<_>>>()?;
+                        if let Some(name) = header.iter().find(|name| !model.has_field(name)) {
+                            bail!("line {start}: {} has no field {name}", model.name);
+                        }
+                        self.header = Some(header);
+                        continue;
+                    };
+                    if cells.len() != header.len() {
+                        let message = format!("expected {} columns, found {}", header.len(), cells.len());
+                        return Ok(Some((start, Err(message))));
+                    }
+                    let row = header.iter()
+                        .zip(cells)
+                        .map(|(name, cell)| {
+                            let value = cell.map_or(Value::Null, Value::String);
+                            (name.clone(), import_value(model, name, value))
+                        })
+                        .collect();
+                    return Ok(Some((start, Ok(row))));
+                }
+                Format::Json => serde_json::from_str::<serde_json::Value>(&line).map_err(|e| e.to_string()),
+                Format::Ron => ron::from_str::<serde_json::Value>(&line).map_err(|e| e.to_string()),
+            };
+            let row = match object {
+                Ok(serde_json::Value::Object(object)) => Ok(object.iter()
+                    .map(|(name, value)| {
+                        let value = json_field_value(model, name, value);
+                        (name.clone(), import_value(model, name, value))
+                    })
+                    .collect()),
+                Ok(value) => Err(format!("expected an object, found {value}")),
+                Err(e) => Err(e),
+            };
+            return Ok(Some((start, row)));
+        }
+    }
+
+    /// The cells of a csv record, `None` for empty unquoted ones. Quoted cells may span lines.
+    fn csv_record(&mut self, mut line: String) -> anyhow::Result<Vec<Option<String>>> {
+        let start = self.line;
+        let mut cells = Vec::new();
+        let mut cell = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        loop {
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if in_quotes && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        cell.push('"');
+                    }
+                    '"' if in_quotes => in_quotes = false,
+                    '"' if cell.is_empty() && !quoted => {
+                        quoted = true;
+                        in_quotes = true;
+                    }
+                    ',' if !in_quotes => {
+                        cells.push((quoted || !cell.is_empty()).then(|| std::mem::take(&mut cell)));
+                        quoted = false;
+                    }
+                    c => cell.push(c),
+                }
+            }
+            if !in_quotes {
+                break;
+            }
+            cell.push('\n');
+            line = self.next_line()?
+                .ok_or_else(|| anyhow!("line {start}: a quoted cell is never closed"))?;
+        }
+        cells.push((quoted || !cell.is_empty()).then_some(cell));
+        Ok(cells)
+    }
+}
+
+/// Reverses how exports write bytes, `\x` prefixed hex, and durations, whole microseconds.
+fn import_value(model: &Model, name: &str, value: Value) -> Value {
+    let data_type = model.get_field(name).map(|field| field.repr.data_type());
+    match (data_type, value) {
+        (Some(DataType::Bytes), Value::String(hex)) if hex.starts_with("\\x") => {
+            let digits = &hex[2..];
+            let decoded = (0..digits.len())
+                .step_by(2)
+                .map(|i| digits.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+                .collect::<Option<Vec<_>>>();
+            match decoded {
+                Some(decoded) => Value::Bytes(decoded),
+                None => Value::String(hex),
+            }
+        }
+        (Some(DataType::Duration), Value::Int(microseconds)) => Value::Duration(TimeDelta::microseconds(microseconds)),
+        (Some(DataType::Duration), Value::String(microseconds)) => match microseconds.parse() {
+            Ok(microseconds) => Value::Duration(TimeDelta::microseconds(microseconds)),
+            Err(_) => Value::String(microseconds),
+        },
+        (_, value) => value,
+    }
+}
+
+/// Numbers and booleans stay as they are, other values are written the way they display, which
+/// converts back to the field's type. Durations are whole microseconds, which is how the drivers
+/// store them.
+fn export_value(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(value) => value.into(),
+        Value::Int(value) => value.into(),
+        Value::Float(value) => value.into(),
+        Value::Duration(value) => match value.num_microseconds() {
+            Some(microseconds) => microseconds.into(),
+            None => value.to_string().into(),
+        },
+        Value::Json(value) => value,
+        value => value.to_string().into(),
+    }
+}
+
+/// Nulls are empty, strings and other values that display as text are quoted.
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::Decimal(_) => value.to_string(),
+        Value::Duration(value) => match value.num_microseconds() {
+            Some(microseconds) => microseconds.to_string(),
+            None => value.to_string(),
+        },
+        value => format!("\"{}\"", value.to_string().replace('"', "\"\"")),
+    }
+}
@@ -1,19 +1,43 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
-use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::SaltString;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::{AeadCore, Key, KeyInit, XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::{Aead, OsRng};
 use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use thiserror::Error;
+use crate::db::quote::{Literal, Quoted};
+use crate::db::value::Value;
 
+/// Types declared in qql files (enums) are registered while validating, so the store is shared
+/// behind a lock like the models in `db::Context`. Projects embedding aio register their own
+/// types with `register` before the qql files are validated.
 pub struct TypeStore {
-    inner: HashMap<String, Rc<dyn Type>>,
+    inner: RwLock<HashMap<String, Arc<dyn Type>>>,
 }
 
 impl Default for TypeStore {
     fn default() -> Self {
-        let mut out = Self::empty();
-        out.inner.insert("UUID".to_owned(), Rc::new(UuidType));
-        out.inner.insert("String".to_owned(), Rc::new(StringType));
-        out.inner.insert("DateTime".to_owned(), Rc::new(DateTimeType));
-        out.inner.insert("Encrypted".to_owned(), Rc::new(EncryptedType));
+        let out = Self::empty();
+        out.insert("UUID", Arc::new(UuidType));
+        out.insert("String", Arc::new(StringType));
+        out.insert("DateTime", Arc::new(DateTimeType));
+        out.insert("Hashed", Arc::new(HashedType));
+        out.insert("Encrypted", Arc::new(EncryptedType::default()));
+        out.insert("Int", Arc::new(IntType));
+        out.insert("BigInt", Arc::new(BigIntType));
+        out.insert("Bool", Arc::new(BoolType));
+        out.insert("Decimal", Arc::new(DecimalType));
+        out.insert("Float", Arc::new(FloatType));
+        out.insert("Date", Arc::new(DateType));
+        out.insert("Time", Arc::new(TimeType));
+        out.insert("Duration", Arc::new(DurationType));
+        out.insert("Json", Arc::new(JsonType));
+        out.insert("Bytes", Arc::new(BytesType));
         out
     }
 }
@@ -21,23 +45,151 @@ impl Default for TypeStore {
 impl TypeStore {
     pub fn empty() -> Self {
         Self {
-            inner: HashMap::default()
+            inner: RwLock::default()
         }
     }
 
-    pub fn get(&self, name: impl AsRef<str>) -> Option<Rc<dyn Type>> {
-        self.inner.get(name.as_ref()).cloned()
+    pub fn get(&self, name: impl AsRef<str>) -> Option<Arc<dyn Type>> {
+        self.inner.read().unwrap().get(name.as_ref()).cloned()
+    }
+
+    #[inline]
+    pub fn contains(&self, name: impl AsRef<str>) -> bool {
+        self.inner.read().unwrap().contains_key(name.as_ref())
+    }
+
+    /// Adds a type usable in model fields, names are unique across builtin and declared types.
+    pub fn register(&self, name: impl Into<String>, type_: Arc<dyn Type>) -> Result<(), DuplicateTypeError> {
+        let name = name.into();
+        let mut inner = self.inner.write().unwrap();
+        if inner.contains_key(&name) {
+            return Err(DuplicateTypeError { name });
+        }
+        inner.insert(name, type_);
+        Ok(())
+    }
+
+    pub(crate) fn insert(&self, name: impl Into<String>, type_: Arc<dyn Type>) {
+        self.inner.write().unwrap().insert(name.into(), type_);
     }
 }
 
+#[derive(Error, Debug)]
+#[error("type {name} is already registered")]
+pub struct DuplicateTypeError {
+    pub name: String,
+}
+
+/// The drivers that create tables, see `Type::column_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverKind {
+    Postgres,
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DataType {
     UUID,
     String,
     DateTime,
+    Int,
+    BigInt,
+    Bool,
+    Decimal,
+    Float,
+    Date,
+    Time,
+    Duration,
+    Json,
+    Bytes,
+    Enum,
+}
+
+impl DataType {
+    /// The name the type is registered under in the default `TypeStore`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataType::UUID => "UUID",
+            DataType::String => "String",
+            DataType::DateTime => "DateTime",
+            DataType::Int => "Int",
+            DataType::BigInt => "BigInt",
+            DataType::Bool => "Bool",
+            DataType::Decimal => "Decimal",
+            DataType::Float => "Float",
+            DataType::Date => "Date",
+            DataType::Time => "Time",
+            DataType::Duration => "Duration",
+            DataType::Json => "Json",
+            DataType::Bytes => "Bytes",
+            DataType::Enum => "Enum",
+        }
+    }
 }
 
-pub trait Type: Debug {
+pub trait Type: Debug + Send + Sync {
+    /// How values are stored and converted, the driver picks the column type from it.
     fn data_type(&self) -> DataType;
+
+    /// Checks the arguments written after the type name, `String(32)` or `Decimal(10, 2)`.
+    fn validate_args(&self, args: &[u64]) -> Result<(), String> {
+        match args {
+            [] => Ok(()),
+            _ => Err("takes no arguments".to_owned()),
+        }
+    }
+
+    /// Checks a value before it is written, it has already been converted to `data_type()`.
+    fn validate(&self, _value: &Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Converts a value written to the field into what is stored.
+    fn encode(&self, value: Value) -> Result<Value, String> {
+        Ok(value)
+    }
+
+    /// Reverses `encode` for values read from the field.
+    fn decode(&self, value: Value) -> Result<Value, String> {
+        Ok(value)
+    }
+
+    /// `decode` cannot reverse `encode`, so exports hold the stored value and imports write it
+    /// without encoding it again.
+    fn one_way(&self) -> bool {
+        false
+    }
+
+    /// Stored values the database cannot compare, like salted hashes, are compared with `verify`
+    /// in rust instead. Queries may only use such fields as `field == #argument` at the top level
+    /// of a select's where clause.
+    fn compared_in_rust(&self) -> bool {
+        false
+    }
+
+    fn verify(&self, stored: &Value, value: &Value) -> Result<bool, String> {
+        Ok(stored == value)
+    }
+
+    /// Overrides the column type `driver` derives from `data_type()`, `column` is the quoted column
+    /// name for use in checks. The driver still appends `not null`.
+    fn column_type(&self, _driver: DriverKind, _column: &str, _args: &[u64]) -> Option<String> {
+        None
+    }
+
+    fn as_enum(&self) -> Option<&EnumType> {
+        None
+    }
+}
+
+/// Accepts no argument or a single maximum length.
+fn validate_length(args: &[u64]) -> Result<(), String> {
+    match args {
+        [] => Ok(()),
+        [0] => Err("length must be at least 1".to_owned()),
+        [_] => Ok(()),
+        _ => Err("takes at most one argument, the maximum length".to_owned()),
+    }
 }
 
 #[derive(Debug, Hash)]
@@ -54,6 +206,10 @@ impl Type for StringType {
     fn data_type(&self) -> DataType {
         DataType::String
     }
+
+    fn validate_args(&self, args: &[u64]) -> Result<(), String> {
+        validate_length(args)
+    }
 }
 
 #[derive(Debug, Hash)]
@@ -64,10 +220,279 @@ impl Type for DateTimeType {
     }
 }
 
+/// Argon2 password hashes in the PHC string format, comparing a field to an argument verifies
+/// the argument against the stored hash.
 #[derive(Debug, Hash)]
-pub struct EncryptedType;
+pub struct HashedType;
+impl Type for HashedType {
+    fn data_type(&self) -> DataType {
+        DataType::String
+    }
+
+    fn encode(&self, value: Value) -> Result<Value, String> {
+        let Value::String(value) = value else {
+            return Ok(value);
+        };
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default().hash_password(value.as_bytes(), &salt)
+            .map(|hash| Value::String(hash.to_string()))
+            .map_err(|e| e.to_string())
+    }
+
+    fn one_way(&self) -> bool {
+        true
+    }
+
+    fn compared_in_rust(&self) -> bool {
+        true
+    }
+
+    fn verify(&self, stored: &Value, value: &Value) -> Result<bool, String> {
+        let (Value::String(stored), Value::String(value)) = (stored, value) else {
+            return Ok(false);
+        };
+        let hash = PasswordHash::new(stored).map_err(|e| e.to_string())?;
+        Ok(Argon2::default().verify_password(value.as_bytes(), &hash).is_ok())
+    }
+}
+
+/// Strings encrypted with XChaCha20-Poly1305, stored as the base64 of the nonce followed by the
+/// ciphertext. Values are encrypted with the first key and decrypted with whichever key
+/// authenticates them, so keys are rotated by prepending a new one.
+#[derive(Default)]
+pub struct EncryptedType {
+    keys: Vec<XChaCha20Poly1305>,
+}
+impl EncryptedType {
+    pub const KEY_LENGTH: usize = 32;
+
+    /// `keys` are base64 encoded, the current key first.
+    pub fn new<S: AsRef<str>>(keys: &[S]) -> Result<Self, String> {
+        let keys = keys.iter()
+            .map(|key| {
+                let key = BASE64.decode(key.as_ref())
+                    .map_err(|e| format!("encryption key is not valid base64: {e}"))?;
+                if key.len() != Self::KEY_LENGTH {
+                    return Err(format!("encryption keys must be {} bytes, found {}", Self::KEY_LENGTH, key.len()));
+                }
+                Ok(XChaCha20Poly1305::new(Key::from_slice(&key)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { keys })
+    }
+
+    fn decrypt(&self, stored: &str) -> Result<String, String> {
+        let stored = BASE64.decode(stored).map_err(|e| format!("invalid encrypted value: {e}"))?;
+        let nonce_length = XNonce::default().len();
+        if stored.len() < nonce_length {
+            return Err("invalid encrypted value: too short".to_owned());
+        }
+        let (nonce, ciphertext) = stored.split_at(nonce_length);
+        let plaintext = self.keys.iter()
+            .find_map(|key| key.decrypt(XNonce::from_slice(nonce), ciphertext).ok())
+            .ok_or("unable to decrypt value, no configured key matches")?;
+        String::from_utf8(plaintext).map_err(|e| e.to_string())
+    }
+}
+impl Debug for EncryptedType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // never print key material
+        f.debug_struct("EncryptedType").field("keys", &self.keys.len()).finish()
+    }
+}
 impl Type for EncryptedType {
     fn data_type(&self) -> DataType {
         DataType::String
     }
+
+    fn encode(&self, value: Value) -> Result<Value, String> {
+        let Value::String(value) = value else {
+            return Ok(value);
+        };
+        let key = self.keys.first()
+            .ok_or("no encryption key is configured, set database.encryption.key")?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = key.encrypt(&nonce, value.as_bytes()).map_err(|e| e.to_string())?;
+
+        let mut stored = nonce.to_vec();
+        stored.extend(ciphertext);
+        Ok(Value::String(BASE64.encode(stored)))
+    }
+
+    fn decode(&self, value: Value) -> Result<Value, String> {
+        match value {
+            Value::String(value) => self.decrypt(&value).map(Value::String),
+            value => Ok(value),
+        }
+    }
+
+    /// every encryption uses a fresh nonce, so equal values are stored differently
+    fn compared_in_rust(&self) -> bool {
+        true
+    }
+
+    fn verify(&self, stored: &Value, value: &Value) -> Result<bool, String> {
+        Ok(self.decode(stored.clone())? == *value)
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct IntType;
+impl Type for IntType {
+    fn data_type(&self) -> DataType {
+        DataType::Int
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct BigIntType;
+impl Type for BigIntType {
+    fn data_type(&self) -> DataType {
+        DataType::BigInt
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct BoolType;
+impl Type for BoolType {
+    fn data_type(&self) -> DataType {
+        DataType::Bool
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct DecimalType;
+impl DecimalType {
+    /// values are held in a `rust_decimal::Decimal`, which has at most 28 significant digits
+    pub const MAX_PRECISION: u64 = 28;
+
+    /// Rounds `value` to the column's scale, failing when the digits left of the point do not fit.
+    pub fn fit(value: Decimal, args: &[u64]) -> Result<Decimal, String> {
+        let (precision, scale) = match args {
+            [precision] => (*precision, 0),
+            [precision, scale] => (*precision, *scale),
+            _ => return Ok(value),
+        };
+        let mut value = value.round_dp(scale as u32);
+        value.rescale(scale as u32);
+        let digits = value.trunc().abs().to_string().trim_start_matches('0').len() as u64;
+        if digits > precision - scale {
+            return Err(format!("numeric field overflow, precision is {precision} with scale {scale}"));
+        }
+        Ok(value)
+    }
+}
+impl Type for DecimalType {
+    fn data_type(&self) -> DataType {
+        DataType::Decimal
+    }
+
+    fn validate_args(&self, args: &[u64]) -> Result<(), String> {
+        let (precision, scale) = match args {
+            [] => return Ok(()),
+            [precision] => (*precision, 0),
+            [precision, scale] => (*precision, *scale),
+            _ => return Err("takes at most two arguments, the precision and scale".to_owned()),
+        };
+        if !(1..=Self::MAX_PRECISION).contains(&precision) {
+            return Err(format!("precision must be between 1 and {}", Self::MAX_PRECISION));
+        }
+        if scale > precision {
+            return Err("scale cannot be larger than the precision".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct FloatType;
+impl Type for FloatType {
+    fn data_type(&self) -> DataType {
+        DataType::Float
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct DateType;
+impl Type for DateType {
+    fn data_type(&self) -> DataType {
+        DataType::Date
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct TimeType;
+impl Type for TimeType {
+    fn data_type(&self) -> DataType {
+        DataType::Time
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct DurationType;
+impl Type for DurationType {
+    fn data_type(&self) -> DataType {
+        DataType::Duration
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct JsonType;
+impl Type for JsonType {
+    fn data_type(&self) -> DataType {
+        DataType::Json
+    }
+}
+
+#[derive(Debug, Hash)]
+pub struct BytesType;
+impl Type for BytesType {
+    fn data_type(&self) -> DataType {
+        DataType::Bytes
+    }
+
+    fn validate_args(&self, args: &[u64]) -> Result<(), String> {
+        validate_length(args)
+    }
+}
+
+/// Declared with `enum Role { Admin, Member }`, values are stored as the variant name.
+#[derive(Debug, Hash)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+impl EnumType {
+    pub fn has_variant(&self, variant: &str) -> bool {
+        self.variants.iter().any(|v| v == variant)
+    }
+}
+impl Type for EnumType {
+    fn data_type(&self) -> DataType {
+        DataType::Enum
+    }
+
+    fn validate(&self, value: &Value) -> Result<(), String> {
+        match value {
+            Value::String(value) if !self.has_variant(value) => {
+                Err(format!("{value:?} is not a variant of {}", self.name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn column_type(&self, driver: DriverKind, column: &str, _args: &[u64]) -> Option<String> {
+        Some(match driver {
+            // created by the postgres driver before the table
+            DriverKind::Postgres => Quoted(&self.name).to_string(),
+            DriverKind::Sqlite => {
+                let variants = self.variants.iter().map(|v| Literal(v).to_string()).collect::<Vec<_>>();
+                format!("text check ({column} in ({}))", variants.join(", "))
+            }
+        })
+    }
+
+    fn as_enum(&self) -> Option<&EnumType> {
+        Some(self)
+    }
 }
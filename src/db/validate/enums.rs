@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use hashbrown::HashSet;
+use crate::db::Context;
+use crate::db::parser;
+use crate::db::types::EnumType;
+use crate::db::validate::ValidationError;
+use crate::parser::Ident;
+
+pub fn validate(context: &Context, enum_: &parser::Enum) -> crate::db::validate::Result<()> {
+    if enum_.variants.is_empty() {
+        return Err(Box::new(ValidationError::EmptyEnum {
+            name: enum_.name.clone(),
+        }));
+    }
+
+    let mut variants = HashSet::<Ident>::new();
+    for variant in &enum_.variants {
+        if !variants.insert(variant.clone()) {
+            return Err(Box::new(ValidationError::DuplicateVariant {
+                name: enum_.name.clone(),
+                variant: variant.clone(),
+            }));
+        }
+    }
+
+    let type_ = EnumType {
+        name: enum_.name.to_string(),
+        variants: enum_.variants.iter().map(Ident::to_string).collect(),
+    };
+    context.type_store().register(enum_.name.as_str(), Arc::new(type_))
+        .map_err(|_| Box::new(ValidationError::DuplicateType {
+            name: enum_.name.clone(),
+        }))
+}
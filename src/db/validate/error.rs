@@ -1,4 +1,5 @@
 use thiserror::Error;
+use crate::db::parser::qql::Action;
 use crate::parser::Ident;
 
 #[derive(Error, Debug, Clone)]
@@ -8,12 +9,59 @@ pub enum ValidationError {
         model: Ident,
         field: Ident,
     },
+    #[error("type {name} is already defined")]
+    DuplicateType {
+        name: Ident,
+    },
+    #[error("model {name} is already defined")]
+    DuplicateModel {
+        name: Ident,
+    },
+    #[error("query {name} is already defined")]
+    DuplicateQuery {
+        name: Ident,
+    },
+    #[error("enum {name} has no variants")]
+    EmptyEnum {
+        name: Ident,
+    },
+    #[error("enum {name} has a duplicated variant {variant:?}")]
+    DuplicateVariant {
+        name: Ident,
+        variant: Ident,
+    },
     #[error("{model}.{field} has unknown type {type_name:?}")]
     UnknownFieldType {
         model: Ident,
         field: Ident,
         type_name: Ident,
     },
+    #[error("{model}.{field} has invalid arguments for {type_name}: {reason}")]
+    InvalidTypeArguments {
+        model: Ident,
+        field: Ident,
+        type_name: Ident,
+        reason: String,
+    },
+    #[error("{model}.{field} has an invalid default: {reason}")]
+    InvalidDefault {
+        model: Ident,
+        field: Ident,
+        reason: String,
+    },
+    #[error("{model}.{field} has unknown attribute @{attribute}")]
+    UnknownAttribute {
+        model: Ident,
+        field: Ident,
+        attribute: Ident,
+    },
+    #[error("{model}.{field} has an invalid attribute @{attribute}: {reason}")]
+    InvalidAttribute {
+        model: Ident,
+        field: Ident,
+        attribute: Ident,
+        reason: String,
+    },
     #[error("query {query} has a duplicate argument {argument:?}")]
     DuplicateQueryArgument {
         query: Ident,
@@ -40,5 +88,113 @@ pub enum ValidationError {
         query: Ident,
         model: Ident,
         field: Ident,
-    }
+    },
+    #[error("query {query} uses {name}.{variant}, however {name} has no variant {variant:?}")]
+    QueryUnknownVariant {
+        query: Ident,
+        name: Ident,
+        variant: Ident,
+    },
+    #[error("query {query} uses {model:?}, however {model:?} is not a model")]
+    QueryUnknownSelector {
+        query: Ident,
+        model: Ident,
+    },
+    #[error("query {query} can only {action:?} a single model")]
+    QueryMultipleSelectors {
+        query: Ident,
+        action: Action,
+    },
+    #[error("query {query} cannot use the quantifier {quantifier} with {action:?}")]
+    QueryInvalidQuantifier {
+        query: Ident,
+        action: Action,
+        quantifier: String,
+    },
+    #[error("query {query} cannot use a where clause with {action:?}")]
+    QueryUnexpectedWhereClause {
+        query: Ident,
+        action: Action,
+    },
+    #[error("query {query} cannot use ORDER BY, OFFSET or AFTER with {action:?}")]
+    QueryUnexpectedOrdering {
+        query: Ident,
+        action: Action,
+    },
+    #[error("query {query} cannot use GROUP BY or HAVING with {action:?}")]
+    QueryUnexpectedGrouping {
+        query: Ident,
+        action: Action,
+    },
+    #[error("query {query} can only use {aggregate} in the selected fields and HAVING")]
+    QueryMisplacedAggregate {
+        query: Ident,
+        aggregate: String,
+    },
+    #[error("query {query} cannot use {aggregate} on {model}.{field}")]
+    QueryInvalidAggregate {
+        query: Ident,
+        aggregate: String,
+        model: Ident,
+        field: Ident,
+    },
+    #[error("query {query} uses {model}.{field} outside an aggregate, however it is not in GROUP BY")]
+    QueryUngroupedField {
+        query: Ident,
+        model: Ident,
+        field: Ident,
+    },
+    #[error("query {query} uses AFTER without ORDER BY, the cursor holds the order fields")]
+    QueryCursorWithoutOrder {
+        query: Ident,
+    },
+    #[error("query {query} must assign a value to {model}.{field} in {action:?}")]
+    QueryMissingFieldValue {
+        query: Ident,
+        action: Action,
+        model: Ident,
+        field: Ident,
+    },
+    #[error("query {query} cannot assign a value to {model}.{field} in {action:?}")]
+    QueryUnexpectedFieldValue {
+        query: Ident,
+        action: Action,
+        model: Ident,
+        field: Ident,
+    },
+    #[error("query {query} can only use {model}.{field} as `{field} == #argument` at the top level of a SELECT where clause")]
+    QueryUnsupportedComparison {
+        query: Ident,
+        model: Ident,
+        field: Ident,
+    },
+    #[error("query {query} updates {model} without setting any of its fields")]
+    QueryEmptyUpdate {
+        query: Ident,
+        model: Ident,
+    },
+    #[error("query {query} does not set {model}.{field}, which is required")]
+    QueryMissingRequiredField {
+        query: Ident,
+        model: Ident,
+        field: Ident,
+    },
+    #[error("query {query} writes an invalid {model}.{field}, it {reason}")]
+    QueryBrokenFieldRule {
+        query: Ident,
+        model: Ident,
+        field: Ident,
+        reason: String,
+    },
+    #[error("query {query} has unknown attribute @{attribute}")]
+    QueryUnknownAttribute {
+        query: Ident,
+        attribute: Ident,
+    },
+    #[error("query {query} has an invalid attribute @{attribute}: {reason}")]
+    QueryInvalidAttribute {
+        query: Ident,
+        attribute: Ident,
+        reason: String,
+    },
 }
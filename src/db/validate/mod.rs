@@ -1,41 +1,126 @@
 mod error;
+pub mod enums;
 pub mod model;
 pub mod query;
 
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context};
 pub use error::ValidationError;
 use crate::db;
 use crate::db::parser::QQLFile;
 
-pub type Result<T> = core::result::Result<T, ValidationError>;
+pub type Result<T> = core::result::Result<T, Box<ValidationError>>;
 
 pub fn validate_file(
     context: &db::Context,
+    namespace: &str,
     file: &QQLFile,
 ) -> Result<()> {
-    for model in file.models.values() {
-        model::validate(context, model)?;
+    validate_files(context, &[(namespace, file)])
+}
+
+/// Every enum and model is validated before any query, so queries can use the models of every
+/// file. Queries are registered under their namespace, `billing.invoice.Unpaid`.
+pub fn validate_files(
+    context: &db::Context,
+    files: &[(&str, &QQLFile)],
+) -> Result<()> {
+    for (_, file) in files {
+        for enum_ in file.enums.values() {
+            enums::validate(context, enum_)?;
+        }
     }
 
-    for query in file.queries.values() {
-        query::validate(context, query)?;
+    for (_, file) in files {
+        for model in file.models.values() {
+            model::validate(context, model)?;
+        }
+    }
+
+    for (namespace, file) in files {
+        for query in file.queries.values() {
+            query::validate(context, namespace, query)?;
+        }
     }
 
     Ok(())
 }
 
+/// Reads the qql files below the database directory, a file's namespace is its path with the
+/// extension removed: `billing/invoice.qql` is `billing.invoice`.
 pub fn validate_database(db_context: &db::Context) -> anyhow::Result<()> {
-    for file in db_context.path.read_dir()? {
-        let file = file?;
+    let mut paths = Vec::new();
+    read_directory(&db_context.path, &["", "qql"], "", &mut paths)?;
+
+    let mut files = Vec::new();
+    for (namespace, path) in paths {
+        let content = std::fs::read_to_string(&path)?;
+        let file: QQLFile = content.parse()
+            .with_context(|| format!("unable to parse {}", path.display()))?;
+        files.push((namespace, file));
+    }
 
-        let file_type = file.file_type()?;
-        if !file_type.is_file() {
+    let files = files.iter()
+        .map(|(namespace, file)| (namespace.as_str(), file))
+        .collect::<Vec<_>>();
+    validate_files(db_context, &files)?;
+    Ok(())
+}
+
+/// Collects the files below `path` that end in one of `extensions`, `""` for files without one,
+/// with the namespace their path gives them.
+pub(crate) fn read_directory(
+    path: &Path,
+    extensions: &[&str],
+    namespace: &str,
+    files: &mut Vec<(String, PathBuf)>,
+) -> anyhow::Result<()> {
+    // sorted so errors are reported in the same order on every platform
+    let mut entries = path.read_dir()?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let Some(name) = namespace_segment(&path, file_type.is_dir(), extensions)? else {
             continue;
-        }
+        };
+        let namespace = match namespace {
+            "" => name,
+            namespace => format!("{namespace}.{name}"),
+        };
 
-        let content = std::fs::read_to_string(file.path())?;
-        let qql_ast: QQLFile = content.parse()?;
-        validate_file(&db_context, &qql_ast)?;
+        if file_type.is_dir() {
+            read_directory(&path, extensions, &namespace, files)?;
+        } else if file_type.is_file() {
+            files.push((namespace, path));
+        }
     }
     Ok(())
 }
 
+/// The part of a namespace a file or directory provides, `None` for hidden entries and files
+/// with an extension other than `extensions`.
+fn namespace_segment(path: &Path, is_dir: bool, extensions: &[&str]) -> anyhow::Result<Option<String>> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        bail!("{} is not valid unicode", path.display());
+    };
+    if name.starts_with('.') {
+        return Ok(None);
+    }
+
+    let name = match name.rsplit_once('.') {
+        _ if is_dir => name,
+        Some((stem, extension)) if extensions.contains(&extension) => stem,
+        Some(_) => return Ok(None),
+        None if extensions.contains(&"") => name,
+        None => return Ok(None),
+    };
+    let mut chars = name.chars();
+    let ident = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !ident {
+        bail!("{} must be named like an identifier to be part of a query name", path.display());
+    }
+    Ok(Some(name.to_owned()))
+}
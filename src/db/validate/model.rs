@@ -1,12 +1,20 @@
 use hashbrown::HashSet;
 use crate::db::Context;
-use crate::db::ast::{Model, ModelField};
-use crate::db::types::TypeStore;
+use crate::db::ast::{Index, Model, ModelField, Timestamp};
+use crate::db::types::{DataType, DecimalType, Type, TypeStore};
+use crate::db::rules::Rule;
+use crate::db::value::Value;
 use crate::db::validate::ValidationError;
 use crate::parser::Ident;
 use crate::db::parser;
 
 pub fn validate(context: &Context, model: &parser::Model) -> crate::db::validate::Result<()> {
+    if context.models().contains_key(&model.name) {
+        return Err(Box::new(ValidationError::DuplicateModel {
+            name: model.name.clone(),
+        }));
+    }
+
     let mut new_model = Model {
         name: model.name.clone(),
         fields: Vec::new(),
@@ -15,20 +23,27 @@ pub fn validate(context: &Context, model: &parser::Model) -> crate::db::validate
     let mut field_names = HashSet::<Ident>::new();
     for field in model.fields.iter() {
         if field_names.contains(field.name.as_str()) {
-            return Err(ValidationError::DuplicateField {
+            return Err(Box::new(ValidationError::DuplicateField {
                 model: model.name.clone(),
                 field: field.name.clone(),
-            });
+            }));
         }
         field_names.insert(field.name.clone());
 
-        let field = validate_field(&context.type_store, &model, field)?;
-        new_model.fields.push(field);
+        let validated = validate_field(context.type_store(), model, field)?;
+        if validated.index == Some(Index::Primary) && new_model.primary_key().is_some() {
+            let attribute = field.attributes.iter().find(|a| a.name == "primary").unwrap();
+            return Err(Box::new(ValidationError::InvalidAttribute {
+                model: model.name.clone(),
+                field: field.name.clone(),
+                attribute: attribute.name.clone(),
+                reason: "the model already has a primary key".to_owned(),
+            }));
+        }
+        new_model.fields.push(validated);
     }
 
-    context.models
-        .borrow_mut()
-        .insert(model.name.clone(), new_model);
+    context.insert_model(new_model);
 
     Ok(())
 }
@@ -44,11 +59,138 @@ pub fn validate_field(
             field: field.name.clone(),
             type_name: field.type_.name.clone(),
         })?;
+    type_.validate_args(&field.type_.args)
+        .map_err(|reason| ValidationError::InvalidTypeArguments {
+            model: model.name.clone(),
+            field: field.name.clone(),
+            type_name: field.type_.name.clone(),
+            reason,
+        })?;
+
+    let (timestamp, index, rules) = validate_attributes(type_.as_ref(), model, field)?;
+    let default = match &field.default {
+        None => None,
+        Some(_) if timestamp.is_some() => return Err(Box::new(ValidationError::InvalidDefault {
+            model: model.name.clone(),
+            field: field.name.clone(),
+            reason: "timestamps are set automatically".to_owned(),
+        })),
+        Some(default) => Some(validate_default(type_.as_ref(), field, default, &rules)
+            .map_err(|reason| ValidationError::InvalidDefault {
+                model: model.name.clone(),
+                field: field.name.clone(),
+                reason,
+            })?),
+    };
 
     Ok(ModelField {
         name: field.name.clone(),
+        type_name: field.type_.name.clone(),
         repr: type_,
         optional: field.type_.optional,
-        arg: field.type_.arg,
+        args: field.type_.args.clone(),
+        default,
+        timestamp,
+        index,
+        rules,
     })
 }
+
+fn validate_attributes(
+    type_: &dyn Type,
+    model: &parser::Model,
+    field: &parser::ModelField,
+) -> crate::db::validate::Result<(Option<Timestamp>, Option<Index>, Vec<Rule>)> {
+    let mut timestamp = None;
+    let mut index = None;
+    let mut rules = Vec::new();
+    for attribute in &field.attributes {
+        let invalid = |reason: &str| Box::new(ValidationError::InvalidAttribute {
+            model: model.name.clone(),
+            field: field.name.clone(),
+            attribute: attribute.name.clone(),
+            reason: reason.to_owned(),
+        });
+        let kind = match attribute.name.as_str() {
+            "index" => Some(Index::Plain),
+            "unique" => Some(Index::Unique),
+            "primary" => Some(Index::Primary),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            if !attribute.args.is_empty() {
+                return Err(invalid("takes no arguments"));
+            }
+            if type_.compared_in_rust() {
+                return Err(invalid("the database cannot compare the stored values"));
+            }
+            if index.is_some() {
+                return Err(invalid("a field has at most one index"));
+            }
+            if kind == Index::Primary && field.type_.optional {
+                return Err(invalid("a primary key cannot be optional"));
+            }
+            index = Some(kind);
+            continue;
+        }
+
+        let value = match attribute.name.as_str() {
+            "created_at" => Timestamp::CreatedAt,
+            "updated_at" => Timestamp::UpdatedAt,
+            name => match Rule::from_attribute(name, &attribute.args, type_.data_type()) {
+                Ok(Some(rule)) => {
+                    rules.push(rule);
+                    continue;
+                }
+                Ok(None) => return Err(Box::new(ValidationError::UnknownAttribute {
+                    model: model.name.clone(),
+                    field: field.name.clone(),
+                    attribute: attribute.name.clone(),
+                })),
+                Err(reason) => return Err(invalid(&reason)),
+            },
+        };
+        if type_.data_type() != DataType::DateTime {
+            return Err(invalid("only DateTime fields can hold a timestamp"));
+        }
+        if !attribute.args.is_empty() {
+            return Err(invalid("takes no arguments"));
+        }
+        if timestamp.is_some() {
+            return Err(invalid("a field holds at most one timestamp"));
+        }
+        timestamp = Some(value);
+    }
+    Ok((timestamp, index, rules))
+}
+
+/// converts the default to the field's type and checks it, like a value written by a query
+fn validate_default(
+    type_: &dyn Type,
+    field: &parser::ModelField,
+    default: &parser::FieldDefault,
+    rules: &[Rule],
+) -> Result<Value, String> {
+    let value = match default {
+        parser::FieldDefault::Literal(Value::Null) if !field.type_.optional => {
+            return Err("the field is not optional".to_owned());
+        }
+        parser::FieldDefault::Literal(value) => value.coerce(type_.data_type())?.into_owned(),
+        parser::FieldDefault::Variant(name, variant) => match type_.as_enum() {
+            Some(enum_) if enum_.name == name.as_str() => Value::String(variant.to_string()),
+            _ => return Err(format!("{name}.{variant} is not a {}", field.type_.name)),
+        },
+    };
+    let value = match value {
+        Value::Decimal(value) => DecimalType::fit(value, &field.type_.args).map(Value::Decimal)?,
+        Value::String(value) if field.type_.args.first().is_some_and(|max| value.chars().count() as u64 > *max) => {
+            return Err("value is too long".to_owned());
+        }
+        value => value,
+    };
+    type_.validate(&value)?;
+    for rule in rules {
+        rule.check(&value)?;
+    }
+    Ok(value)
+}
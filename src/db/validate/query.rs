@@ -1,17 +1,33 @@
 use hashbrown::HashSet;
 use crate::db::{Context, parser};
+use crate::db::ast::{ModelField, Query};
 use crate::db::parser::qql;
+use crate::db::parser::qql::Action;
+use crate::db::types::DataType;
+use crate::db::value::Value;
 use crate::db::validate::ValidationError;
 use crate::parser::Ident;
 
-pub(crate) fn validate(context: &Context, query: &parser::Query) -> super::Result<()> {
+/// Registers the query as `namespace.Name`, or `Name` in the empty namespace.
+pub(crate) fn validate(context: &Context, namespace: &str, query: &parser::Query) -> super::Result<()> {
+    let name = match namespace {
+        "" => query.name.clone(),
+        namespace => Ident {
+            value: format!("{namespace}.{}", query.name),
+            ..query.name.clone()
+        },
+    };
+    if context.queries().contains_key(&name) {
+        return Err(Box::new(ValidationError::DuplicateQuery { name }));
+    }
+
     let mut args = HashSet::<Ident>::new();
     for arg in &query.args {
         if args.contains(arg) {
-            return Err(ValidationError::DuplicateQueryArgument {
+            return Err(Box::new(ValidationError::DuplicateQueryArgument {
                 query: query.name.clone(),
                 argument: arg.clone(),
-            });
+            }));
         }
         args.insert(arg.clone());
     }
@@ -21,13 +37,72 @@ pub(crate) fn validate(context: &Context, query: &parser::Query) -> super::Resul
         _ => None,
     };
 
+    let statement = &query.statement;
+    let grouped = statement.action == Action::Select && (
+        !statement.group_by.is_empty()
+            || statement.having.is_some()
+            || statement.selectors.iter().flat_map(|s| &s.fields).any(|f| f.aggregate.is_some())
+    );
+
     let mut query_context = QueryContext {
         context,
         query,
         args: &args,
         principal_model,
+        grouped,
+        in_having: false,
     };
     query_context.validate_quantifier(&query.statement.quantifier)?;
+    query_context.validate_selectors(&query.statement.selectors)?;
+    if let Some(where_clause) = &query.statement.where_clause {
+        if query.statement.action == Action::Insert {
+            return Err(Box::new(ValidationError::QueryUnexpectedWhereClause {
+                query: query.name.clone(),
+                action: query.statement.action,
+            }));
+        }
+        query_context.validate_expr(&where_clause.expr)?;
+        // rows compared in rust are filtered after the database has grouped them
+        let top_level = query.statement.action == Action::Select && !grouped;
+        query_context.validate_compared_in_rust(&where_clause.expr, top_level)?;
+    }
+    query_context.validate_grouping(&query.statement)?;
+    query_context.validate_ordering(&query.statement)?;
+    // `@cache(duration)` is the only attribute queries take, and only selects
+    let mut cache = None;
+    for attribute in &query.attributes {
+        let invalid = |reason: &str| Box::new(ValidationError::QueryInvalidAttribute {
+            query: query.name.clone(),
+            attribute: attribute.name.clone(),
+            reason: reason.to_owned(),
+        });
+        if attribute.name != "cache" {
+            return Err(Box::new(ValidationError::QueryUnknownAttribute {
+                query: query.name.clone(),
+                attribute: attribute.name.clone(),
+            }));
+        }
+        if cache.is_some() {
+            return Err(invalid("the query is already cached"));
+        }
+        if query.statement.action != Action::Select {
+            return Err(invalid("only the results of SELECT can be cached"));
+        }
+        let duration = match attribute.args.as_slice() {
+            [Value::Duration(duration)] => duration.to_std().ok().filter(|duration| !duration.is_zero()),
+            _ => None,
+        };
+        cache = Some(duration.ok_or_else(|| invalid("expected a positive duration, like @cache(60s)"))?);
+    }
+
+    let mut statement = query.statement.clone();
+    resolve_variants(context, &mut statement);
+    context.insert_query(Query {
+        name,
+        args: query.args.clone(),
+        statement,
+        cache,
+    });
 
     Ok(())
 }
@@ -38,19 +113,253 @@ struct QueryContext<'a> {
     args: &'a HashSet<Ident>,
 
     principal_model: Option<Ident>,
+    /// the statement aggregates rows, so fields must be grouped or aggregated
+    grouped: bool,
+    /// aggregates are only allowed in selected fields and `HAVING`
+    in_having: bool,
 }
 
 impl<'a> QueryContext<'a> {
     fn validate_quantifier(&mut self, quantifier: &qql::Quantifier) -> super::Result<()> {
+        let action = self.query.statement.action;
+        let invalid = match (action, quantifier) {
+            (Action::Select, _) => None,
+            (Action::Insert, qql::Quantifier::One) => None,
+            // writes are not limited, `ONE` would still change every matching row
+            (Action::Update | Action::Delete, qql::Quantifier::All) => None,
+            (_, qql::Quantifier::One) => Some("ONE".to_owned()),
+            (_, qql::Quantifier::All) => Some("ALL".to_owned()),
+            (_, qql::Quantifier::Number(n)) => Some(n.to_string()),
+            (_, qql::Quantifier::Expr(_)) => Some("<expression>".to_owned()),
+        };
+        if let Some(quantifier) = invalid {
+            return Err(Box::new(ValidationError::QueryInvalidQuantifier {
+                query: self.query.name.clone(),
+                action,
+                quantifier,
+            }));
+        }
+
         match quantifier {
             qql::Quantifier::Expr(expr) => {
                 self.validate_expr(expr)?;
+                self.validate_compared_in_rust(expr, false)?;
                 Ok(())
             }
             _ => Ok(())
         }
     }
 
+    fn validate_selectors(&mut self, selectors: &[qql::Selector]) -> super::Result<()> {
+        let action = self.query.statement.action;
+        if action != Action::Select && selectors.len() > 1 {
+            return Err(Box::new(ValidationError::QueryMultipleSelectors {
+                query: self.query.name.clone(),
+                action,
+            }));
+        }
+
+        for selector in selectors {
+            let model = self.context.models()
+                .get(&selector.name)
+                .cloned()
+                .ok_or_else(|| ValidationError::QueryUnknownSelector {
+                    query: self.query.name.clone(),
+                    model: selector.name.clone(),
+                })?;
+
+            for field in &selector.fields {
+                let Some(model_field) = model.get_field(&field.name) else {
+                    return Err(Box::new(ValidationError::QueryUnknownField {
+                        query: self.query.name.clone(),
+                        model: model.name.clone(),
+                        field: field.name.clone(),
+                    }));
+                };
+
+                if let Some(aggregate) = field.aggregate {
+                    if action != Action::Select {
+                        return Err(Box::new(ValidationError::QueryMisplacedAggregate {
+                            query: self.query.name.clone(),
+                            aggregate: aggregate.function().to_ascii_uppercase(),
+                        }));
+                    }
+                    self.validate_aggregate(aggregate, &model.name, model_field)?;
+                } else if self.grouped {
+                    self.validate_grouped(&model.name, &field.name)?;
+                }
+
+                match (action, &field.value) {
+                    (Action::Insert | Action::Update, None) => {
+                        return Err(Box::new(ValidationError::QueryMissingFieldValue {
+                            query: self.query.name.clone(),
+                            action,
+                            model: model.name.clone(),
+                            field: field.name.clone(),
+                        }));
+                    }
+                    (Action::Select | Action::Delete, Some(_)) => {
+                        return Err(Box::new(ValidationError::QueryUnexpectedFieldValue {
+                            query: self.query.name.clone(),
+                            action,
+                            model: model.name.clone(),
+                            field: field.name.clone(),
+                        }));
+                    }
+                    (_, Some(value)) => {
+                        self.validate_expr(value)?;
+                        self.validate_compared_in_rust(value, false)?;
+                        // arguments are checked against the rules when the query runs
+                        let broken = literal(value)
+                            .and_then(|value| value.coerce(model_field.repr.data_type()).ok().map(|value| value.into_owned()))
+                            .and_then(|value| model_field.rules.iter().find_map(|rule| rule.check(&value).err()));
+                        if let Some(reason) = broken {
+                            return Err(Box::new(ValidationError::QueryBrokenFieldRule {
+                                query: self.query.name.clone(),
+                                model: model.name.clone(),
+                                field: field.name.clone(),
+                                reason,
+                            }));
+                        }
+                    }
+                    (_, None) => {}
+                }
+            }
+
+            if selector.fields.is_empty() && action == Action::Update {
+                return Err(Box::new(ValidationError::QueryEmptyUpdate {
+                    query: self.query.name.clone(),
+                    model: model.name.clone(),
+                }));
+            }
+            if selector.fields.is_empty() && self.grouped {
+                for field in &model.fields {
+                    self.validate_grouped(&model.name, &field.name)?;
+                }
+            }
+
+            if action == Action::Insert {
+                let missing = model.fields.iter()
+                    .filter(|field| !field.optional && field.repr.data_type() != DataType::UUID)
+                    .filter(|field| field.default.is_none() && field.timestamp.is_none())
+                    .find(|field| !selector.fields.iter().any(|f| f.name == field.name));
+                if let Some(field) = missing {
+                    return Err(Box::new(ValidationError::QueryMissingRequiredField {
+                        query: self.query.name.clone(),
+                        model: model.name.clone(),
+                        field: field.name.clone(),
+                    }));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_grouping(&mut self, statement: &qql::Statement) -> super::Result<()> {
+        if (!statement.group_by.is_empty() || statement.having.is_some()) && statement.action != Action::Select {
+            return Err(Box::new(ValidationError::QueryUnexpectedGrouping {
+                query: self.query.name.clone(),
+                action: statement.action,
+            }));
+        }
+
+        for group in &statement.group_by {
+            let field = qql::Expr::Field(group.model.clone(), group.field.clone());
+            self.validate_expr(&field)?;
+            self.validate_compared_in_rust(&field, false)?;
+        }
+
+        if let Some(having) = &statement.having {
+            self.in_having = true;
+            let valid = self.validate_expr(having);
+            self.in_having = false;
+            valid?;
+            self.validate_compared_in_rust(having, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_aggregate(&self, aggregate: qql::Aggregate, model: &Ident, field: &ModelField) -> super::Result<()> {
+        let data_type = field.repr.data_type();
+        let numeric = matches!(data_type, DataType::Int | DataType::BigInt | DataType::Decimal | DataType::Float);
+        let valid = match aggregate {
+            qql::Aggregate::Count => true,
+            qql::Aggregate::Sum | qql::Aggregate::Avg => numeric,
+            qql::Aggregate::Min | qql::Aggregate::Max => !field.repr.compared_in_rust() && (numeric || matches!(
+                data_type,
+                DataType::String | DataType::DateTime | DataType::Date | DataType::Time | DataType::Duration,
+            )),
+        };
+        if !valid {
+            return Err(Box::new(ValidationError::QueryInvalidAggregate {
+                query: self.query.name.clone(),
+                aggregate: aggregate.function().to_ascii_uppercase(),
+                model: model.clone(),
+                field: field.name.clone(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// fields used outside an aggregate in a grouped statement must be in `GROUP BY`
+    fn validate_grouped(&self, model: &Ident, field: &Ident) -> super::Result<()> {
+        let grouped = self.query.statement.group_by.iter().any(|group| {
+            group.field == *field && group.model.as_ref().or(self.principal_model.as_ref()) == Some(model)
+        });
+        if !grouped {
+            return Err(Box::new(ValidationError::QueryUngroupedField {
+                query: self.query.name.clone(),
+                model: model.clone(),
+                field: field.clone(),
+            }));
+        }
+        Ok(())
+    }
+
+    fn validate_ordering(&mut self, statement: &qql::Statement) -> super::Result<()> {
+        let ordered = !statement.order_by.is_empty() || statement.offset.is_some() || statement.after.is_some();
+        if ordered && statement.action != Action::Select {
+            return Err(Box::new(ValidationError::QueryUnexpectedOrdering {
+                query: self.query.name.clone(),
+                action: statement.action,
+            }));
+        }
+
+        for order in &statement.order_by {
+            if let Some(model) = order.model.as_ref().filter(|model| self.is_enum_reference(model)) {
+                return Err(Box::new(ValidationError::QueryUnknownModel {
+                    query: self.query.name.clone(),
+                    model: model.clone(),
+                    field: order.field.clone(),
+                }));
+            }
+            let field = qql::Expr::Field(order.model.clone(), order.field.clone());
+            self.validate_expr(&field)?;
+            self.validate_compared_in_rust(&field, false)?;
+            if let Some(model) = order.model.as_ref().or(self.principal_model.as_ref()).filter(|_| self.grouped) {
+                self.validate_grouped(model, &order.field)?;
+            }
+        }
+
+        if let Some(offset) = &statement.offset {
+            self.validate_expr(offset)?;
+            self.validate_compared_in_rust(offset, false)?;
+        }
+
+        if let Some(after) = &statement.after {
+            if statement.order_by.is_empty() {
+                return Err(Box::new(ValidationError::QueryCursorWithoutOrder {
+                    query: self.query.name.clone(),
+                }));
+            }
+            self.validate_expr(&qql::Expr::Interp(after.clone()))?;
+        }
+
+        Ok(())
+    }
+
     fn validate_expr(&mut self, expr: &qql::Expr) -> super::Result<()> {
         match expr {
             qql::Expr::Binary(l, _, r) => {
@@ -58,51 +367,244 @@ impl<'a> QueryContext<'a> {
                 self.validate_expr(r.as_ref())?;
                 Ok(())
             }
-            qql::Expr::Unary(_, r) => {
+            qql::Expr::Unary(_, r) | qql::Expr::IsNull(r, _) => {
                 self.validate_expr(r.as_ref())?;
                 Ok(())
             }
-            qql::Expr::Number(_) => Ok(()),
+            qql::Expr::In(expr, values) => {
+                self.validate_expr(expr)?;
+                for value in values {
+                    self.validate_expr(value)?;
+                }
+                Ok(())
+            }
+            qql::Expr::InArgument(expr, arg) => {
+                self.validate_expr(expr)?;
+                self.validate_expr(&qql::Expr::Interp(arg.clone()))
+            }
+            qql::Expr::Between(expr, low, high) => {
+                self.validate_expr(expr)?;
+                self.validate_expr(low)?;
+                self.validate_expr(high)
+            }
+            qql::Expr::Number(_) | qql::Expr::String(_) | qql::Expr::Bool(_) | qql::Expr::Null => Ok(()),
             qql::Expr::Interp(var) => {
                 if !self.args.contains(var) {
-                    Err(ValidationError::UnknownQueryVariable {
+                    Err(Box::new(ValidationError::UnknownQueryVariable {
                         query: self.query.name.clone(),
                         variable: var.clone(),
-                    })
+                    }))
                 } else {
                     Ok(())
                 }
             }
+            qql::Expr::Field(Some(name), variant) if self.is_enum_reference(name) => {
+                let type_ = self.context.type_store.get(name).unwrap();
+                if type_.as_enum().is_some_and(|enum_| enum_.has_variant(variant)) {
+                    Ok(())
+                } else {
+                    Err(Box::new(ValidationError::QueryUnknownVariant {
+                        query: self.query.name.clone(),
+                        name: name.clone(),
+                        variant: variant.clone(),
+                    }))
+                }
+            }
+            qql::Expr::Variant(..) => Ok(()),
+            qql::Expr::Aggregate(aggregate, model, field) => {
+                if !self.in_having {
+                    return Err(Box::new(ValidationError::QueryMisplacedAggregate {
+                        query: self.query.name.clone(),
+                        aggregate: aggregate.function().to_ascii_uppercase(),
+                    }));
+                }
+                self.in_having = false;
+                let valid = self.validate_expr(&qql::Expr::Field(model.clone(), field.clone()));
+                self.in_having = true;
+                valid?;
+
+                // the field exists, or validating it above would have failed
+                let model = model.as_ref().or(self.principal_model.as_ref()).unwrap();
+                let models = self.context.models();
+                let field = models.get(model).and_then(|m| m.get_field(field)).unwrap();
+                self.validate_aggregate(*aggregate, model, field)
+            }
             qql::Expr::Field(model, field) => {
+                if self.in_having {
+                    if let Some(model) = model.as_ref().or(self.principal_model.as_ref()) {
+                        self.validate_grouped(model, field)?;
+                    }
+                }
                 match (&self.principal_model, model) {
                     (_, Some(model)) | (Some(model), _) => {
-                        let models = self.context.models.borrow();
+                        let models = self.context.models();
                         match models.get(model) {
                             Some(model) => {
                                 if !model.has_field(field) {
-                                    Err(ValidationError::QueryUnknownField {
+                                    Err(Box::new(ValidationError::QueryUnknownField {
                                         query: self.query.name.clone(),
                                         model: model.name.clone(),
                                         field: field.clone(),
-                                    })
+                                    }))
                                 } else {
                                     Ok(())
                                 }
                             }
-                            None => Err(ValidationError::QueryUnknownModel {
+                            None => Err(Box::new(ValidationError::QueryUnknownModel {
                                 query: self.query.name.clone(),
                                 model: model.clone(),
                                 field: field.clone(),
-                            }),
+                            })),
                         }
                     }
-                    (None, None) => Err(ValidationError::AmbiguousQueryField {
+                    (None, None) => Err(Box::new(ValidationError::AmbiguousQueryField {
                         query: self.query.name.clone(),
                         field: field.clone(),
-                    })
+                    }))
                 }
             }
             qql::Expr::Group(inner) => self.validate_expr(inner.as_ref()),
         }
     }
+
+    /// Fields whose type is `compared_in_rust` are only allowed in `field == #argument` at the
+    /// top level of a select's where clause, the only place the compiler can check them after
+    /// the database returns rows.
+    fn validate_compared_in_rust(&self, expr: &qql::Expr, top_level: bool) -> super::Result<()> {
+        match expr {
+            qql::Expr::Binary(l, qql::BinaryOp::And, r) => {
+                self.validate_compared_in_rust(l, top_level)?;
+                self.validate_compared_in_rust(r, top_level)
+            }
+            qql::Expr::Binary(l, qql::BinaryOp::Eq, r) if top_level && matches!(
+                (l.as_ref(), r.as_ref()),
+                (qql::Expr::Field(..), qql::Expr::Interp(_)) | (qql::Expr::Interp(_), qql::Expr::Field(..)),
+            ) => Ok(()),
+            qql::Expr::Binary(l, _, r) => {
+                self.validate_compared_in_rust(l, false)?;
+                self.validate_compared_in_rust(r, false)
+            }
+            qql::Expr::Unary(_, inner) => self.validate_compared_in_rust(inner, false),
+            qql::Expr::Group(inner) => self.validate_compared_in_rust(inner, top_level),
+            // the database sees whether a value is stored, whatever it stores
+            qql::Expr::IsNull(inner, _) if matches!(inner.as_ref(), qql::Expr::Field(..)) => Ok(()),
+            qql::Expr::IsNull(inner, _) | qql::Expr::InArgument(inner, _) => self.validate_compared_in_rust(inner, false),
+            qql::Expr::In(inner, values) => {
+                self.validate_compared_in_rust(inner, false)?;
+                for value in values {
+                    self.validate_compared_in_rust(value, false)?;
+                }
+                Ok(())
+            }
+            qql::Expr::Between(inner, low, high) => {
+                self.validate_compared_in_rust(inner, false)?;
+                self.validate_compared_in_rust(low, false)?;
+                self.validate_compared_in_rust(high, false)
+            }
+            qql::Expr::Field(model, field) => {
+                let Some(model) = model.as_ref().or(self.principal_model.as_ref()) else {
+                    return Ok(());
+                };
+                let compared_in_rust = self.context.models().get(model)
+                    .and_then(|model| model.get_field(field))
+                    .is_some_and(|field| field.repr.compared_in_rust());
+                if compared_in_rust {
+                    return Err(Box::new(ValidationError::QueryUnsupportedComparison {
+                        query: self.query.name.clone(),
+                        model: model.clone(),
+                        field: field.clone(),
+                    }));
+                }
+                Ok(())
+            }
+            // aggregates of these fields are rejected by `validate_aggregate`
+            qql::Expr::Number(_)
+            | qql::Expr::String(_)
+            | qql::Expr::Bool(_)
+            | qql::Expr::Null
+            | qql::Expr::Interp(_)
+            | qql::Expr::Variant(..)
+            | qql::Expr::Aggregate(..) => Ok(()),
+        }
+    }
+
+    /// `Role.Admin` names an enum variant when `Role` is an enum rather than a model
+    fn is_enum_reference(&self, name: &Ident) -> bool {
+        is_enum_reference(self.context, name)
+    }
+}
+
+/// The value a literal writes, `None` for anything only known when the query runs.
+fn literal(expr: &qql::Expr) -> Option<Value> {
+    match expr {
+        qql::Expr::String(value) => Some(Value::String(value.clone())),
+        qql::Expr::Number(value) => i64::try_from(*value).ok().map(Value::Int),
+        qql::Expr::Unary(qql::UnaryOp::Negative, inner) => match literal(inner)? {
+            Value::Int(value) => value.checked_neg().map(Value::Int),
+            _ => None,
+        },
+        qql::Expr::Group(inner) => literal(inner),
+        _ => None,
+    }
+}
+
+fn is_enum_reference(context: &Context, name: &Ident) -> bool {
+    !context.models().contains_key(name)
+        && context.type_store.get(name).is_some_and(|type_| type_.as_enum().is_some())
+}
+
+/// rewrites the `Enum.Variant` fields of a validated statement so drivers don't need the type store
+fn resolve_variants(context: &Context, statement: &mut qql::Statement) {
+    fn resolve(context: &Context, expr: &mut qql::Expr) {
+        match expr {
+            qql::Expr::Binary(lhs, _, rhs) => {
+                resolve(context, lhs);
+                resolve(context, rhs);
+            }
+            qql::Expr::Unary(_, inner)
+            | qql::Expr::Group(inner)
+            | qql::Expr::IsNull(inner, _)
+            | qql::Expr::InArgument(inner, _) => resolve(context, inner),
+            qql::Expr::In(inner, values) => {
+                resolve(context, inner);
+                values.iter_mut().for_each(|value| resolve(context, value));
+            }
+            qql::Expr::Between(inner, low, high) => {
+                resolve(context, inner);
+                resolve(context, low);
+                resolve(context, high);
+            }
+            qql::Expr::Field(Some(name), variant) if is_enum_reference(context, name) => {
+                *expr = qql::Expr::Variant(name.clone(), variant.clone());
+            }
+            qql::Expr::Number(_)
+            | qql::Expr::String(_)
+            | qql::Expr::Bool(_)
+            | qql::Expr::Null
+            | qql::Expr::Interp(_)
+            | qql::Expr::Field(..)
+            | qql::Expr::Variant(..)
+            | qql::Expr::Aggregate(..) => {}
+        }
+    }
+
+    if let qql::Quantifier::Expr(expr) = &mut statement.quantifier {
+        resolve(context, expr);
+    }
+    for selector in &mut statement.selectors {
+        for field in &mut selector.fields {
+            if let Some(value) = &mut field.value {
+                resolve(context, value);
+            }
+        }
+    }
+    if let Some(where_clause) = &mut statement.where_clause {
+        resolve(context, &mut where_clause.expr);
+    }
+    if let Some(having) = &mut statement.having {
+        resolve(context, having);
+    }
+    if let Some(offset) = &mut statement.offset {
+        resolve(context, offset);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use uuid::Uuid;
+use crate::db::types::DataType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    String(String),
+    Uuid(Uuid),
+    DateTime(NaiveDateTime),
+    Float(f64),
+    Decimal(Decimal),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Duration(TimeDelta),
+    Json(serde_json::Value),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    pub const DATE_TIME_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S%.f";
+    pub const DATE_FORMAT: &'static str = "%Y-%m-%d";
+    pub const TIME_FORMAT: &'static str = "%H:%M:%S%.f";
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// The column type that holds this value without conversion, `None` for null.
+    pub fn data_type(&self) -> Option<DataType> {
+        Some(match self {
+            Value::Null => return None,
+            Value::Bool(_) => DataType::Bool,
+            Value::Int(_) => DataType::BigInt,
+            Value::String(_) => DataType::String,
+            Value::Uuid(_) => DataType::UUID,
+            Value::DateTime(_) => DataType::DateTime,
+            Value::Float(_) => DataType::Float,
+            Value::Decimal(_) => DataType::Decimal,
+            Value::Date(_) => DataType::Date,
+            Value::Time(_) => DataType::Time,
+            Value::Duration(_) => DataType::Duration,
+            Value::Json(_) => DataType::Json,
+            Value::Bytes(_) => DataType::Bytes,
+        })
+    }
+
+    /// Converts the value into the representation stored in a `data_type` column, strings are
+    /// parsed so arguments written as text (like on the command line) can fill any column.
+    pub fn coerce(&self, data_type: DataType) -> Result<Cow<'_, Value>, String> {
+        let value = match (data_type, self) {
+            (_, Value::Null) => return Ok(Cow::Borrowed(self)),
+            (DataType::UUID, Value::Uuid(_))
+            | (DataType::String, Value::String(_))
+            | (DataType::DateTime, Value::DateTime(_))
+            | (DataType::BigInt, Value::Int(_))
+            | (DataType::Bool, Value::Bool(_))
+            | (DataType::Decimal, Value::Decimal(_))
+            | (DataType::Float, Value::Float(_))
+            | (DataType::Date, Value::Date(_))
+            | (DataType::Time, Value::Time(_))
+            | (DataType::Duration, Value::Duration(_))
+            | (DataType::Json, Value::Json(_))
+            | (DataType::Bytes, Value::Bytes(_))
+            // variants are checked against the field's `EnumType`
+            | (DataType::Enum, Value::String(_)) => return Ok(Cow::Borrowed(self)),
+
+            (DataType::Int, Value::Int(value)) => match i32::try_from(*value) {
+                Ok(_) => return Ok(Cow::Borrowed(self)),
+                Err(_) => return Err(format!("{value} does not fit in an Int")),
+            },
+            (DataType::UUID, Value::String(value)) => Uuid::parse_str(value)
+                .map(Value::Uuid)
+                .map_err(|e| e.to_string())?,
+            (DataType::DateTime, Value::String(value)) => NaiveDateTime::parse_from_str(value, Self::DATE_TIME_FORMAT)
+                .map(Value::DateTime)
+                .map_err(|e| e.to_string())?,
+            (DataType::Int, Value::String(value)) => i32::from_str(value)
+                .map(|value| Value::Int(value.into()))
+                .map_err(|e| e.to_string())?,
+            (DataType::BigInt, Value::String(value)) => i64::from_str(value)
+                .map(Value::Int)
+                .map_err(|e| e.to_string())?,
+            (DataType::Bool, Value::String(value)) => bool::from_str(value)
+                .map(Value::Bool)
+                .map_err(|e| e.to_string())?,
+            (DataType::Decimal, Value::Int(value)) => Value::Decimal(Decimal::from(*value)),
+            (DataType::Decimal, Value::Float(value)) => Decimal::try_from(*value)
+                .map(Value::Decimal)
+                .map_err(|e| e.to_string())?,
+            (DataType::Decimal, Value::String(value)) => Decimal::from_str_exact(value)
+                .map(Value::Decimal)
+                .map_err(|e| e.to_string())?,
+            (DataType::Float, Value::Int(value)) => Value::Float(*value as f64),
+            (DataType::Float, Value::Decimal(value)) => Value::Float(value.to_f64().ok_or("decimal is out of range")?),
+            (DataType::Float, Value::String(value)) => f64::from_str(value)
+                .map(Value::Float)
+                .map_err(|e| e.to_string())?,
+            (DataType::Date, Value::String(value)) => NaiveDate::parse_from_str(value, Self::DATE_FORMAT)
+                .map(Value::Date)
+                .map_err(|e| e.to_string())?,
+            (DataType::Time, Value::String(value)) => NaiveTime::parse_from_str(value, Self::TIME_FORMAT)
+                .map(Value::Time)
+                .map_err(|e| e.to_string())?,
+            // whole seconds
+            (DataType::Duration, Value::Int(value)) => TimeDelta::try_seconds(*value)
+                .map(Value::Duration)
+                .ok_or("duration is out of range")?,
+            (DataType::Json, Value::String(value)) => serde_json::from_str(value)
+                .map(Value::Json)
+                .map_err(|e| e.to_string())?,
+            (DataType::Json, Value::Bool(value)) => Value::Json((*value).into()),
+            (DataType::Json, Value::Int(value)) => Value::Json((*value).into()),
+            (DataType::Json, Value::Float(value)) => Value::Json((*value).into()),
+            (DataType::Bytes, Value::String(value)) => Value::Bytes(value.as_bytes().to_vec()),
+            (data_type, value) => return Err(format!("expected {}, found {value:?}", data_type.name())),
+        };
+        Ok(Cow::Owned(value))
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Int(value) => write!(f, "{value}"),
+            Value::String(value) => write!(f, "{value}"),
+            Value::Uuid(value) => write!(f, "{value}"),
+            Value::DateTime(value) => write!(f, "{}", value.format(Self::DATE_TIME_FORMAT)),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Decimal(value) => write!(f, "{value}"),
+            Value::Date(value) => write!(f, "{}", value.format(Self::DATE_FORMAT)),
+            Value::Time(value) => write!(f, "{}", value.format(Self::TIME_FORMAT)),
+            Value::Duration(value) => write!(f, "{value}"),
+            Value::Json(value) => write!(f, "{value}"),
+            Value::Bytes(value) => {
+                write!(f, "\\x")?;
+                value.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub affected: u64,
+}
@@ -4,7 +4,9 @@ use std::fmt::Debug;
 use std::fs::File;
 use std::io::BufWriter;
 use std::path::{Path};
-use anyhow::{anyhow, Context};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{bail, Context};
 use crate::cli::{CLI, Command, DatabaseCommand, DatabaseMigrationCommand};
 use crate::config::Config;
 use crate::web::Service;
@@ -17,34 +19,33 @@ mod util;
 mod simpl;
 mod web;
 
-fn main() -> anyhow::Result<()> {
+/// written to the build directory, so every build starts a new session
+const QUERY_STATS: &str = "query_stats.json";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
     let mut cli = <CLI as clap::Parser>::parse();
     cli.path = cli.path.canonicalize()?;
 
     match &cli.command {
         Command::Check {} => check(&cli.path),
-        Command::Build {} => build(&cli.path),
+        Command::Build {} => build(&cli.path).await,
         Command::Db { command: DatabaseCommand::Query { expression } } => {
             let config = Config::from_directory(cli.path.clone())?;
             let db_context = db::Context::from_config(&config)?;
 
-            let path = expression.split('.').collect::<Vec<_>>();
-            let (query_expr, query_path) = path.split_last()
-                .context("expected path to query: db.example.GetExample()")?;
+            let call: db::parser::QueryCall = expression.parse()
+                .context("expected query call: example.GetExample()")?;
 
-            let mut file_path = db_context.path.clone();
-            for link in query_path {
-                file_path = file_path.join(link);
-            }
-            let file: db::parser::QQLFile = std::fs::read_to_string(file_path)?.parse()?;
-            db::validate::validate_file(&db_context, &file)?;
+            db::validate::validate_database(&db_context)?;
 
-            // todo: use the actual parser
-            let query_name = query_expr.strip_suffix("()").unwrap();
-            let query = file.queries.get(query_name)
-                .ok_or_else(|| anyhow!("no query named {:?} in {}", query_expr, query_path.join(".")))?;
+            let name = call.qualified_name();
+            if !db_context.queries().contains_key(name.as_str()) {
+                bail!("no query named {:?} in {}", name, db_context.path.display());
+            }
 
-            println!("{query:#?}");
+            let result = db_context.execute(&name, &call.args).await?;
+            print_query_result(&result);
 
             Ok(())
         }
@@ -54,15 +55,107 @@ fn main() -> anyhow::Result<()> {
             db::validate::validate_database(&db_context)?;
             match command {
                 DatabaseMigrationCommand::Up { .. } => {
-                    db::migrate::migrate_up(&db_context)?;
+                    db::migrate::migrate_up(&db_context).await?;
                     Ok(())
                 }
                 DatabaseMigrationCommand::Down { .. } => {
-                    db::migrate::migrate_down(&db_context)?;
+                    db::migrate::migrate_down(&db_context).await?;
                     Ok(())
                 }
             }
         }
+        Command::Db { command: DatabaseCommand::Pull { force } } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+            db::pull::pull(&db_context, *force).await?;
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Codegen { out } } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+            db::validate::validate_database(&db_context)?;
+            db::codegen::codegen(&db_context, out)?;
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Docs } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+            db::validate::validate_database(&db_context)?;
+
+            let target = cli.path.join("build");
+            create_clean_target(&target)?;
+            db::docs::docs(&db_context, &target)?;
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Explain { expression, analyze } } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+
+            let (name, args) = match expression.contains('(') {
+                true => {
+                    let call: db::parser::QueryCall = expression.parse()
+                        .context("expected query name or call: example.GetExample()")?;
+                    (call.qualified_name(), Some(call.args))
+                }
+                false => (expression.clone(), None),
+            };
+
+            db::validate::validate_database(&db_context)?;
+            print!("{}", db::explain::explain(&db_context, &name, args, *analyze).await?);
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Seed { sets } } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+            db::validate::validate_database(&db_context)?;
+
+            for seeded in db::seed::seed(&db_context, sets).await? {
+                println!(
+                    "seeded {} ({}): {} inserted, {} already present",
+                    seeded.name, seeded.model, seeded.inserted, seeded.skipped,
+                );
+            }
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Export { model, format, out } } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+            db::validate::validate_database(&db_context)?;
+
+            match out {
+                Some(out) => {
+                    let mut file = BufWriter::new(File::create(out)?);
+                    let count = db::transfer::export(&db_context, model, *format, &mut file).await?;
+                    eprintln!("wrote {count} rows to {}", out.display());
+                }
+                None => {
+                    let mut stdout = BufWriter::new(std::io::stdout());
+                    db::transfer::export(&db_context, model, *format, &mut stdout).await?;
+                }
+            }
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Import { model, file, format } } => {
+            let config = Config::from_directory(cli.path.clone())?;
+            let db_context = db::Context::from_config(&config)?;
+            db::validate::validate_database(&db_context)?;
+
+            let Some(format) = format.or_else(|| db::transfer::Format::from_extension(file)) else {
+                bail!("unable to tell the format of {}, pass --format", file.display());
+            };
+            let input = std::io::BufReader::new(File::open(file)?);
+            let count = db::transfer::import(&db_context, model, format, input).await?;
+            println!("imported {count} rows into {model}");
+            Ok(())
+        }
+        Command::Db { command: DatabaseCommand::Stats } => {
+            let file = cli.path.join("build").join(QUERY_STATS);
+            if !file.exists() {
+                bail!("no query stats in {}, they are written while `aio build` serves", file.display());
+            }
+            print!("{}", db::log::SessionStats::load(&file)?.summary());
+            Ok(())
+        }
     }
 }
 
@@ -75,24 +168,67 @@ fn check(path: &Path) -> anyhow::Result<()> {
     let web_context = web::Context::from_config(&config)?;
     web::validate::validate(&web_context)?;
 
+    let used_queries = web::validate::used_queries(&web_context)?;
+    let findings = db::lint::lint(&db_context, &used_queries);
+    for finding in &findings {
+        eprintln!("{finding}");
+    }
+    let denied = findings.iter().filter(|f| f.severity == db::lint::Severity::Deny).count();
+    if denied > 0 {
+        bail!("{denied} denied schema lints, see database.lints in the project file");
+    }
+
     Ok(())
 }
 
-fn build(path: &Path) -> anyhow::Result<()> {
+async fn build(path: &Path) -> anyhow::Result<()> {
     let config = Config::from_directory(path.to_path_buf())?;
 
     create_clean_target(path.join("build"))?;
 
     let db_context = db::Context::from_config(&config)?;
     db::validate::validate_database(&db_context)?;
-    db::migrate::migrate_up(&db_context)?;
+    db::migrate::migrate_up(&db_context).await?;
 
     let web_context = web::Context::from_config(&config)?;
     web::validate::validate(&web_context)?;
-    let service = Service::try_new(web_context.clone())?;
-    service.run()?;
+    let db_context = Arc::new(db_context);
+    let service = Service::try_new(web_context.clone(), db_context.clone())?;
+
+    // saved while serving too, so `aio db stats` can report on a running session
+    let stats_file = path.join("build").join(QUERY_STATS);
+    let saver = tokio::spawn({
+        let db_context = db_context.clone();
+        let stats_file = stats_file.clone();
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                if let Err(e) = db_context.query_log().save(&stats_file) {
+                    eprintln!("unable to save query stats: {e}");
+                }
+            }
+        }
+    });
+    let result = service.run().await;
+    saver.abort();
+    db_context.query_log().save(&stats_file)?;
+    result
+}
 
-    Ok(())
+fn print_query_result(result: &db::value::QueryResult) {
+    if result.columns.is_empty() {
+        println!("{} rows affected", result.affected);
+        return;
+    }
+
+    println!("{}", result.columns.join("\t"));
+    for row in &result.rows {
+        let row = row.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        println!("{}", row.join("\t"));
+    }
 }
 
 fn create_clean_target(path: impl AsRef<Path>) -> anyhow::Result<()> {
@@ -451,6 +451,7 @@ pub enum Expr {
     Block(Vec<Expr>),
     Html(Vec<Section>),
     Respond(Vec<(Ident, Expr)>),
+    Transaction(Vec<Expr>),
 }
 
 #[derive(Debug)]
@@ -670,6 +671,16 @@ impl<'a> SimplParser<'a> {
                 Ok(Expr::Bool(false))
             } else if ident == "if" {
                 self.parse_expression_if().map(Expr::If)
+            } else if ident == "respond" {
+                self.inner.expect('(')?;
+                let fields = self.parse_separated_terminated(')', ',', Self::parse_respond_field)?;
+                self.inner.expect(')')?;
+                Ok(Expr::Respond(fields))
+            } else if ident == "transaction" {
+                self.inner.expect('{')?;
+                let body = self.parse_until('}', SimplParser::parse_expression)?;
+                self.inner.expect('}')?;
+                Ok(Expr::Transaction(body))
             } else {
                 Ok(Expr::Ident(ident))
             }
@@ -733,6 +744,14 @@ impl<'a> SimplParser<'a> {
         })
     }
 
+    fn parse_respond_field(&mut self) -> Result<(Ident, Expr), ParseError> {
+        let name = self.parse_ident()
+            .ok_or_else(|| ParseError::new("expected response field", self.inner.location))?;
+        self.inner.expect('=')?;
+        let value = self.parse_expression_or()?;
+        Ok((name, value))
+    }
+
     fn parse_html_block(&mut self) -> Result<Vec<Section>, ParseError> {
         let mut out = Vec::new();
         loop {
@@ -18,4 +18,21 @@ pub enum RuntimeError {
         path: PathBuf,
         error: ParseError
     },
+    #[error("unknown name: {name}")]
+    UnknownName {
+        name: Ident,
+    },
+    #[error("invalid operation: {message}")]
+    InvalidOperation {
+        message: String,
+    },
+    #[error("query {query} failed: {error}")]
+    QueryError {
+        query: Ident,
+        error: anyhow::Error,
+    },
+    #[error("transaction failed: {error}")]
+    TransactionError {
+        error: anyhow::Error,
+    },
 }
\ No newline at end of file
@@ -0,0 +1,405 @@
+use async_recursion::async_recursion;
+use crate::db;
+use crate::parser::Ident;
+use crate::simpl::parser::{AttributeValue, BinaryOp, Else, Expr, HtmlElement, If, Section, UnaryOp};
+use crate::simpl::runtime::{Response, Runtime};
+use crate::simpl::runtime::error::RuntimeError;
+use crate::simpl::runtime::value::Value;
+
+fn invalid(message: impl Into<String>) -> RuntimeError {
+    RuntimeError::InvalidOperation { message: message.into() }
+}
+
+impl Runtime {
+    /// Evaluates each expression in order, stopping early once a response has been sent.
+    #[async_recursion]
+    pub async fn eval_block(&mut self, body: &[Expr]) -> Result<Value, RuntimeError> {
+        let mut last = Value::Null;
+        for expr in body {
+            last = self.eval(expr).await?;
+            if self.response.is_some() {
+                break;
+            }
+        }
+        Ok(last)
+    }
+
+    #[async_recursion]
+    pub async fn eval(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        Ok(match expr {
+            Expr::Binary(op, lhs, rhs) => self.eval_binary(op, lhs, rhs).await?,
+            Expr::Unary(op, expr) => self.eval_unary(op, expr).await?,
+            Expr::If(if_) => self.eval_if(if_).await?,
+            Expr::Ident(name) => self.scope.get(name.as_str())
+                .cloned()
+                .ok_or_else(|| RuntimeError::UnknownName { name: name.clone() })?,
+            Expr::Number(value) => Value::Int(i64::try_from(*value)
+                .map_err(|_| invalid(format!("{value} does not fit in an int")))?),
+            Expr::String(value) => Value::String(value.clone()),
+            Expr::Decimal(value) => Value::Decimal(*value),
+            Expr::Bool(value) => Value::Bool(*value),
+            Expr::Path(path) => Value::String(path.display().to_string()),
+            Expr::Group(expr) => self.eval(expr).await?,
+            Expr::Block(body) => self.eval_scoped(body).await?,
+            Expr::Html(sections) => {
+                let mut out = String::new();
+                self.render(&mut out, sections).await?;
+                Value::Html(out)
+            }
+            Expr::Respond(fields) => {
+                let mut response = Response::default();
+                for (name, value) in fields {
+                    let value = self.eval(value).await?;
+                    match name.as_str() {
+                        "statusCode" => response.status = Self::status_code(value)?,
+                        "body" => response.body = value.to_string(),
+                        _ => return Err(invalid(format!("unknown response field {name}"))),
+                    }
+                }
+                self.response = Some(response);
+                Value::Null
+            }
+            Expr::Transaction(body) => self.eval_transaction(body).await?,
+        })
+    }
+
+    async fn eval_scoped(&mut self, body: &[Expr]) -> Result<Value, RuntimeError> {
+        self.scope.push();
+        let value = self.eval_block(body).await;
+        self.scope.pop();
+        value
+    }
+
+    /// Commits when the body completes normally, rolls back on an error, an error response or a
+    /// write that broke field rules. Nested transactions run inside the outermost one.
+    async fn eval_transaction(&mut self, body: &[Expr]) -> Result<Value, RuntimeError> {
+        if self.transaction.is_some() {
+            return self.eval_scoped(body).await;
+        }
+
+        let transaction = self.database.begin().await
+            .map_err(|error| RuntimeError::TransactionError { error })?;
+        self.transaction = Some(transaction);
+        self.rollback_only = false;
+        let result = self.eval_scoped(body).await;
+        let transaction = self.transaction.take().unwrap();
+
+        let failed = self.rollback_only
+            || self.response.as_ref().is_some_and(|response| response.status >= 400);
+        match result {
+            Ok(_) if failed => transaction.rollback().await,
+            Ok(_) => transaction.commit().await,
+            // the original error is more useful than a failed rollback
+            Err(error) => {
+                let _ = transaction.rollback().await;
+                return Err(error);
+            }
+        }.map_err(|error| RuntimeError::TransactionError { error })?;
+
+        result
+    }
+
+    #[async_recursion]
+    async fn eval_if(&mut self, if_: &If) -> Result<Value, RuntimeError> {
+        let condition = self.eval(&if_.condition).await?;
+        if Self::truthy(&condition)? {
+            return self.eval_scoped(&if_.then).await;
+        }
+        match &if_.otherwise {
+            Some(Else::If(if_)) => self.eval_if(if_).await,
+            Some(Else::Block(body)) => self.eval_scoped(body).await,
+            None => Ok(Value::Null),
+        }
+    }
+
+    async fn eval_binary(&mut self, op: &BinaryOp, lhs: &Expr, rhs: &Expr) -> Result<Value, RuntimeError> {
+        match op {
+            BinaryOp::Assign => {
+                let Expr::Ident(name) = lhs else {
+                    return Err(invalid("only names can be assigned to"));
+                };
+                let value = self.eval(rhs).await?;
+                self.scope.set(name.as_str(), value);
+                return Ok(Value::Null);
+            }
+            BinaryOp::And | BinaryOp::Or => {
+                let lhs = self.eval(lhs).await?;
+                let lhs = Self::truthy(&lhs)?;
+                if lhs == matches!(op, BinaryOp::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                let rhs = self.eval(rhs).await?;
+                return Ok(Value::Bool(Self::truthy(&rhs)?));
+            }
+            _ => {}
+        }
+
+        let lhs = self.eval(lhs).await?;
+        let rhs = self.eval(rhs).await?;
+        Ok(match (op, lhs, rhs) {
+            (BinaryOp::Eq, lhs, rhs) => Value::Bool(lhs == rhs),
+            (BinaryOp::Ne, lhs, rhs) => Value::Bool(lhs != rhs),
+            (BinaryOp::Add, Value::String(lhs), Value::String(rhs)) => Value::String(lhs + &rhs),
+            (BinaryOp::Add, Value::List(mut lhs), Value::List(rhs)) => {
+                lhs.extend(rhs);
+                Value::List(lhs)
+            }
+            (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, Value::String(lhs), Value::String(rhs)) => {
+                Value::Bool(Self::compare(op, lhs.cmp(&rhs)))
+            }
+            (op, Value::Int(lhs), Value::Int(rhs)) => match op {
+                BinaryOp::Add => lhs.checked_add(rhs).map(Value::Int).ok_or_else(|| invalid("integer overflow"))?,
+                BinaryOp::Sub => lhs.checked_sub(rhs).map(Value::Int).ok_or_else(|| invalid("integer overflow"))?,
+                BinaryOp::Mul => lhs.checked_mul(rhs).map(Value::Int).ok_or_else(|| invalid("integer overflow"))?,
+                BinaryOp::Div => lhs.checked_div(rhs).map(Value::Int).ok_or_else(|| invalid("division by zero"))?,
+                BinaryOp::Rem => lhs.checked_rem(rhs).map(Value::Int).ok_or_else(|| invalid("division by zero"))?,
+                op => Value::Bool(Self::compare(op, lhs.cmp(&rhs))),
+            },
+            (op, lhs @ (Value::Int(_) | Value::Decimal(_)), rhs @ (Value::Int(_) | Value::Decimal(_))) => {
+                let (lhs, rhs) = (Self::decimal(&lhs), Self::decimal(&rhs));
+                match op {
+                    BinaryOp::Add => Value::Decimal(lhs + rhs),
+                    BinaryOp::Sub => Value::Decimal(lhs - rhs),
+                    BinaryOp::Mul => Value::Decimal(lhs * rhs),
+                    BinaryOp::Div => Value::Decimal(lhs / rhs),
+                    BinaryOp::Rem => Value::Decimal(lhs % rhs),
+                    op => match lhs.partial_cmp(&rhs) {
+                        Some(ordering) => Value::Bool(Self::compare(op, ordering)),
+                        None => Value::Bool(false),
+                    },
+                }
+            }
+            (op, lhs, rhs) => return Err(invalid(format!(
+                "{op:?} is not supported between {} and {}", lhs.type_name(), rhs.type_name(),
+            ))),
+        })
+    }
+
+    async fn eval_unary(&mut self, op: &UnaryOp, expr: &Expr) -> Result<Value, RuntimeError> {
+        if let UnaryOp::Call(args) = op {
+            return self.eval_call(expr, args).await;
+        }
+
+        let value = self.eval(expr).await?;
+        Ok(match (op, value) {
+            (UnaryOp::Not, value) => Value::Bool(!Self::truthy(&value)?),
+            (UnaryOp::Negative, Value::Int(value)) => Value::Int(value.checked_neg()
+                .ok_or_else(|| invalid("integer overflow"))?),
+            (UnaryOp::Negative, Value::Decimal(value)) => Value::Decimal(-value),
+            (UnaryOp::Access(name), value) => value.field(name.as_str())
+                .cloned()
+                .ok_or_else(|| invalid(format!("{} has no field {name}", value.type_name())))?,
+            (UnaryOp::Index(index), value) => {
+                let index = self.eval(index).await?;
+                match (&value, &index) {
+                    (Value::List(values), Value::Int(i)) => usize::try_from(*i).ok()
+                        .and_then(|i| values.get(i))
+                        .cloned()
+                        .ok_or_else(|| invalid(format!("index {i} is out of bounds")))?,
+                    (Value::Record(_), Value::String(name)) => value.field(name)
+                        .cloned()
+                        .ok_or_else(|| invalid(format!("record has no field {name}")))?,
+                    _ => return Err(invalid(format!(
+                        "{} cannot be indexed by {}", value.type_name(), index.type_name(),
+                    ))),
+                }
+            }
+            (UnaryOp::Unwrap, Value::Null) => return Err(invalid("unwrapped a null value")),
+            (UnaryOp::Unwrap, value) => value,
+            (op, value) => return Err(invalid(format!("{op:?} is not supported on {}", value.type_name()))),
+        })
+    }
+
+    /// Only database queries are callable for now: `db.user.Login(username, password)`.
+    async fn eval_call(&mut self, callee: &Expr, args: &[Expr]) -> Result<Value, RuntimeError> {
+        let mut path = Vec::new();
+        let mut expr = callee;
+        while let Expr::Unary(UnaryOp::Access(name), inner) = expr {
+            path.push(name);
+            expr = inner;
+        }
+        let name = match expr {
+            // the path was collected from the query name outwards
+            Expr::Ident(root) if root == "db" && !path.is_empty() => Ident {
+                value: path.iter().rev().map(|ident| ident.as_str()).collect::<Vec<_>>().join("."),
+                ..path[0].clone()
+            },
+            Expr::Ident(name) => return Err(RuntimeError::UnknownName { name: name.clone() }),
+            _ => return Err(invalid("only queries can be called")),
+        };
+
+        let queries = self.database.queries();
+        let query = queries.get(name.as_str())
+            .ok_or_else(|| RuntimeError::UnknownName { name: name.clone() })?;
+
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            let value = self.eval(arg).await?;
+            let value = db::value::Value::try_from(value)
+                .map_err(|value| invalid(format!("a {} cannot be passed to a query", value.type_name())))?;
+            values.push(value);
+        }
+
+        let result = match &mut self.transaction {
+            Some(transaction) => self.database.execute_in(transaction.as_mut(), &name, &values).await,
+            None => self.database.execute(&name, &values).await,
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(error) => match error.downcast::<db::rules::FieldErrors>() {
+                Ok(errors) => {
+                    // the earlier writes of the transaction must not commit without this one
+                    if self.transaction.is_some() {
+                        self.rollback_only = true;
+                    }
+                    self.set_field_errors(&errors);
+                    return Ok(Value::Null);
+                }
+                Err(error) => return Err(RuntimeError::QueryError { query: name.clone(), error }),
+            },
+        };
+
+        Ok(Self::query_value(query, result))
+    }
+
+    /// A write that breaks field rules evaluates to null and sets `errors` to a record with every
+    /// field of the model, holding the first failed rule's message or null.
+    fn set_field_errors(&mut self, errors: &db::rules::FieldErrors) {
+        let models = self.database.models();
+        let Some(model) = models.get(&errors.model) else {
+            return;
+        };
+        let fields = model.fields.iter()
+            .map(|field| {
+                let message = errors.errors.iter()
+                    .find(|error| error.field == field.name)
+                    .map_or(Value::Null, |error| Value::String(error.message.clone()));
+                (field.name.to_string(), message)
+            })
+            .collect();
+        self.scope.set("errors", Value::Record(fields));
+    }
+
+    fn query_value(query: &db::ast::Query, result: db::value::QueryResult) -> Value {
+        use db::parser::qql::{Action, Quantifier};
+
+        let statement = &query.statement;
+        if statement.action != Action::Select {
+            return Value::Int(result.affected as i64);
+        }
+
+        let mut records = result.rows.into_iter()
+            .map(|row| {
+                let fields = result.columns.iter()
+                    .cloned()
+                    .zip(row.into_iter().map(Value::from))
+                    .collect();
+                Value::Record(fields)
+            });
+        match statement.quantifier {
+            Quantifier::One => records.next().unwrap_or(Value::Null),
+            _ => Value::List(records.collect()),
+        }
+    }
+
+    #[async_recursion]
+    async fn render(&mut self, out: &mut String, sections: &[Section]) -> Result<(), RuntimeError> {
+        for section in sections {
+            match section {
+                Section::Element(element) => self.render_element(out, element).await?,
+                Section::Escaped(expr) => match self.eval(expr).await? {
+                    Value::Html(html) => out.push_str(&html),
+                    value => escape(out, &value.to_string()),
+                },
+                Section::Unescaped(expr) => out.push_str(&self.eval(expr).await?.to_string()),
+                Section::Text(text) => out.push_str(text),
+            }
+        }
+        Ok(())
+    }
+
+    async fn render_element(&mut self, out: &mut String, element: &HtmlElement) -> Result<(), RuntimeError> {
+        out.push('<');
+        out.push_str(element.name.as_str());
+        for (name, value) in &element.attributes {
+            out.push(' ');
+            out.push_str(name.as_str());
+            match value {
+                // character references are kept as written
+                Some(AttributeValue::String(value)) => {
+                    out.push_str("=\"");
+                    out.push_str(value);
+                    out.push('"');
+                }
+                Some(AttributeValue::Expr(expr)) => {
+                    let value = self.eval(expr).await?;
+                    out.push_str("=\"");
+                    escape(out, &value.to_string());
+                    out.push('"');
+                }
+                None => {}
+            }
+        }
+
+        match &element.body {
+            Some(body) => {
+                out.push('>');
+                self.render(out, body).await?;
+                out.push_str("</");
+                out.push_str(element.name.as_str());
+                out.push('>');
+            }
+            None => out.push_str("/>"),
+        }
+        Ok(())
+    }
+
+    fn truthy(value: &Value) -> Result<bool, RuntimeError> {
+        match value {
+            Value::Bool(value) => Ok(*value),
+            Value::Null => Ok(false),
+            // so templates can write `if errors { ... }`
+            Value::List(values) => Ok(!values.is_empty()),
+            Value::Record(fields) => Ok(!fields.is_empty()),
+            value => Err(invalid(format!("expected a bool, found {}", value.type_name()))),
+        }
+    }
+
+    fn decimal(value: &Value) -> f64 {
+        match value {
+            Value::Int(value) => *value as f64,
+            Value::Decimal(value) => *value,
+            _ => unreachable!(),
+        }
+    }
+
+    fn compare(op: &BinaryOp, ordering: std::cmp::Ordering) -> bool {
+        match op {
+            BinaryOp::Lt => ordering.is_lt(),
+            BinaryOp::Le => ordering.is_le(),
+            BinaryOp::Gt => ordering.is_gt(),
+            BinaryOp::Ge => ordering.is_ge(),
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) fn status_code(value: Value) -> Result<u16, RuntimeError> {
+        match value {
+            Value::Int(code @ 100..=999) => Ok(code as u16),
+            value => Err(invalid(format!("{value:?} is not a valid status code"))),
+        }
+    }
+}
+
+fn escape(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
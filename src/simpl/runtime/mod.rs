@@ -2,28 +2,77 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use async_recursion::async_recursion;
 use hashbrown::{HashMap, HashSet};
+use crate::db;
+use crate::db::backend::Transaction;
 use crate::simpl::parser::{Import, SimplFile};
 use crate::simpl::runtime::error::RuntimeError;
 use crate::simpl::runtime::scope::Scope;
+use crate::simpl::runtime::value::Value;
 use crate::web::Context;
 
 pub mod scope;
 pub mod error;
+pub mod value;
+mod eval;
 
 pub struct Runtime {
     context: Arc<Context>,
+    database: Arc<db::Context>,
     scope: Scope,
     processed_files: HashSet<FileId>,
+    transaction: Option<Box<dyn Transaction>>,
+    /// a write in the transaction broke field rules, so it rolls back
+    rollback_only: bool,
+    response: Option<Response>,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self {
+            status: 200,
+            body: String::new(),
+        }
+    }
 }
 
 impl Runtime {
-    pub fn new(context: Arc<Context>) -> Self {
+    pub fn new(context: Arc<Context>, database: Arc<db::Context>) -> Self {
+        // null until a query breaks field rules, see `set_field_errors`
+        let mut scope = Scope::default();
+        scope.set("errors", Value::Null);
+
         Self {
             context,
-            scope: Scope::default(),
+            database,
+            scope,
             processed_files: Default::default(),
+            transaction: None,
+            rollback_only: false,
+            response: None,
         }
     }
+
+    /// The response sent with `respond`, otherwise the `statusCode` and `body` the file assigned.
+    pub fn into_response(self) -> Result<Response, RuntimeError> {
+        if let Some(response) = self.response {
+            return Ok(response);
+        }
+
+        let mut response = Response::default();
+        if let Some(status) = self.scope.get("statusCode") {
+            response.status = Self::status_code(status.clone())?;
+        }
+        if let Some(body) = self.scope.get("body") {
+            response.body = body.to_string();
+        }
+        Ok(response)
+    }
 }
 
 #[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Clone)]
@@ -58,6 +107,7 @@ impl Runtime {
     pub async fn run(&mut self, file: &SimplFile) -> Result<(), RuntimeError> {
         let file_id = FileId::new("<main>");
         self.process_file(file_id, file).await?;
+        self.eval_block(&file.statements).await?;
         Ok(())
     }
 
@@ -132,4 +182,117 @@ impl Runtime {
 
         Ok(file_path.clone())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use crate::db;
+    use crate::db::parser::QQLFile;
+    use crate::db::validate;
+    use crate::simpl::parser::SimplFile;
+    use crate::web::context::{CodeMap, Context, RouteMap};
+    use super::*;
+
+    const QQL: &str = r#"
+        model Note {
+            note_id: Int @primary,
+            title: String @min(3)
+        }
+
+        query AddNote(note_id, title) {
+            INSERT ONE Note(note_id = #note_id, title = #title)
+        }
+
+        query Notes() {
+            SELECT ALL Note(note_id)
+        }
+    "#;
+
+    fn database() -> Arc<db::Context> {
+        let database = db::Context::memory();
+        validate::validate_file(&database, "", &QQL.parse::<QQLFile>().unwrap()).unwrap();
+        Arc::new(database)
+    }
+
+    /// Runs the handler and returns its result with the number of notes left afterwards.
+    async fn run(source: &str) -> (Result<Response, RuntimeError>, usize) {
+        let context = Arc::new(Context {
+            address: "127.0.0.1:0".parse().unwrap(),
+            serve_dir: PathBuf::new(),
+            route_map: RouteMap::default(),
+            shared_code: CodeMap::default(),
+        });
+        let database = database();
+        let mut runtime = Runtime::new(context, database.clone());
+        let file = source.parse::<SimplFile>().unwrap();
+        let result = match runtime.run(&file).await {
+            Ok(()) => runtime.into_response(),
+            Err(error) => Err(error),
+        };
+        let notes = database.execute("Notes", &[]).await.unwrap().rows.len();
+        (result, notes)
+    }
+
+    #[tokio::test]
+    async fn transactions_commit_when_the_block_finishes() {
+        let (result, notes) = run(r#"
+            transaction {
+                db.AddNote(1, "first")
+                db.AddNote(2, "second")
+            }
+        "#).await;
+        assert_eq!(result.unwrap().status, 200);
+        assert_eq!(notes, 2);
+    }
+
+    #[tokio::test]
+    async fn transactions_roll_back_on_a_runtime_error() {
+        let (result, notes) = run(r#"
+            transaction {
+                db.AddNote(1, "first")
+                missing
+            }
+        "#).await;
+        assert!(matches!(result, Err(RuntimeError::UnknownName { .. })));
+        assert_eq!(notes, 0);
+    }
+
+    #[tokio::test]
+    async fn transactions_roll_back_on_an_error_response() {
+        let (result, notes) = run(r#"
+            transaction {
+                db.AddNote(1, "first")
+                respond(statusCode = 400, body = "no")
+                db.AddNote(2, "second")
+            }
+        "#).await;
+        let response = result.unwrap();
+        assert_eq!((response.status, response.body.as_str()), (400, "no"));
+        assert_eq!(notes, 0);
+    }
+
+    #[tokio::test]
+    async fn transactions_commit_on_a_successful_response() {
+        let (result, notes) = run(r#"
+            transaction {
+                db.AddNote(1, "first")
+                respond(statusCode = 201)
+            }
+        "#).await;
+        assert_eq!(result.unwrap().status, 201);
+        assert_eq!(notes, 1);
+    }
+
+    #[tokio::test]
+    async fn transactions_roll_back_when_a_write_breaks_field_rules() {
+        let (result, notes) = run(r#"
+            transaction {
+                db.AddNote(1, "first")
+                db.AddNote(2, "no")
+            }
+        "#).await;
+        assert_eq!(result.unwrap().status, 200);
+        assert_eq!(notes, 0);
+    }
+}
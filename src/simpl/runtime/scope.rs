@@ -1,5 +1,6 @@
 use std::collections::LinkedList;
 use hashbrown::HashMap;
+use crate::simpl::runtime::value::Value;
 
 pub struct Scope(LinkedList<ScopeFrame>);
 
@@ -13,5 +14,29 @@ impl Default for Scope {
 
 #[derive(Default)]
 pub struct ScopeFrame {
-    locals: HashMap<String, ()>,
+    locals: HashMap<String, Value>,
+}
+
+impl Scope {
+    pub fn push(&mut self) {
+        self.0.push_front(ScopeFrame::default());
+    }
+
+    pub fn pop(&mut self) {
+        self.0.pop_front();
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.iter().find_map(|frame| frame.locals.get(name))
+    }
+
+    /// Assigns to the innermost frame that already defines `name`, otherwise defines it in the
+    /// current frame.
+    pub fn set(&mut self, name: &str, value: Value) {
+        if let Some(local) = self.0.iter_mut().find_map(|frame| frame.locals.get_mut(name)) {
+            *local = value;
+        } else if let Some(frame) = self.0.front_mut() {
+            frame.locals.insert(name.to_owned(), value);
+        }
+    }
 }
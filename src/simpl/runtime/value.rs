@@ -0,0 +1,140 @@
+use std::fmt::{Display, Formatter};
+use rust_decimal::prelude::ToPrimitive;
+use crate::db;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Decimal(f64),
+    String(String),
+    /// already rendered markup, inserted into other html without escaping
+    Html(String),
+    List(Vec<Value>),
+    Record(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Decimal(_) => "decimal",
+            Value::String(_) => "string",
+            Value::Html(_) => "html",
+            Value::List(_) => "list",
+            Value::Record(_) => "record",
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Record(fields) => fields.iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Decimal(value) => write!(f, "{value}"),
+            Value::String(value) | Value::Html(value) => f.write_str(value),
+            Value::List(values) => values.iter().try_for_each(|value| write!(f, "{value}")),
+            Value::Record(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl From<db::value::Value> for Value {
+    fn from(value: db::value::Value) -> Self {
+        match value {
+            db::value::Value::Null => Value::Null,
+            db::value::Value::Bool(value) => Value::Bool(value),
+            db::value::Value::Int(value) => Value::Int(value),
+            db::value::Value::String(value) => Value::String(value),
+            db::value::Value::Float(value) => Value::Decimal(value),
+            // simpl decimals are floating point, precision beyond an f64 is lost
+            db::value::Value::Decimal(value) => Value::Decimal(value.to_f64().unwrap_or(f64::NAN)),
+            db::value::Value::Json(value) => value.into(),
+            db::value::Value::Bytes(value) => Value::List(value.into_iter().map(|byte| Value::Int(byte.into())).collect()),
+            value @ (db::value::Value::Uuid(_)
+            | db::value::Value::DateTime(_)
+            | db::value::Value::Date(_)
+            | db::value::Value::Time(_)
+            | db::value::Value::Duration(_)) => Value::String(value.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(value) => Value::Bool(value),
+            serde_json::Value::Number(value) => match value.as_i64() {
+                Some(value) => Value::Int(value),
+                None => Value::Decimal(value.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(value) => Value::String(value),
+            serde_json::Value::Array(values) => Value::List(values.into_iter().map(Value::from).collect()),
+            serde_json::Value::Object(fields) => Value::Record(
+                fields.into_iter().map(|(name, value)| (name, value.into())).collect()
+            ),
+        }
+    }
+}
+
+impl TryFrom<Value> for serde_json::Value {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(value) => value.into(),
+            Value::Int(value) => value.into(),
+            Value::Decimal(value) => value.into(),
+            Value::String(value) => value.into(),
+            Value::List(values) => values.into_iter()
+                .map(serde_json::Value::try_from)
+                .collect::<Result<_, _>>()?,
+            Value::Record(fields) => fields.into_iter()
+                .map(|(name, value)| Ok((name, serde_json::Value::try_from(value)?)))
+                .collect::<Result<serde_json::Map<_, _>, Value>>()?
+                .into(),
+            value @ Value::Html(_) => return Err(value),
+        })
+    }
+}
+
+impl TryFrom<Value> for db::value::Value {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            Value::Null => db::value::Value::Null,
+            Value::Bool(value) => db::value::Value::Bool(value),
+            Value::Int(value) => db::value::Value::Int(value),
+            Value::String(value) => db::value::Value::String(value),
+            Value::Decimal(value) => db::value::Value::Float(value),
+            value @ (Value::List(_) | Value::Record(_)) => db::value::Value::Json(value.try_into()?),
+            value @ Value::Html(_) => return Err(value),
+        })
+    }
+}
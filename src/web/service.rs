@@ -14,7 +14,9 @@ use hyper::service::service_fn;
 use hyper_util::rt::TokioIo;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::select;
+use crate::db;
 use crate::simpl::parser::SimplFile;
+use crate::simpl::runtime;
 use crate::simpl::runtime::Runtime;
 use crate::web::Context;
 
@@ -22,6 +24,7 @@ use crate::web::context::RouteMap;
 
 pub struct Service {
     context: Arc<Context>,
+    database: Arc<db::Context>,
     address: SocketAddr,
     router: Arc<Router>,
 }
@@ -49,7 +52,7 @@ impl Router {
 }
 
 impl Service {
-    pub fn try_new(context: Arc<Context>) -> anyhow::Result<Self> {
+    pub fn try_new(context: Arc<Context>, database: Arc<db::Context>) -> anyhow::Result<Self> {
         let mut router = Router::new();
         Self::build_router(&mut router.inner, &context.route_map)?;
 
@@ -57,6 +60,7 @@ impl Service {
             address: context.address.clone(),
             router: Arc::new(router),
             context,
+            database,
         })
     }
 
@@ -76,56 +80,51 @@ impl Service {
         Ok(())
     }
 
-    pub fn run(self) -> anyhow::Result<()> {
-        let rt = tokio::runtime::Runtime::new()?;
-
-        rt.block_on(async move {
-            let router = self.router.clone();
-            let addr = self.address;
-            let context = self.context;
-            let listener = TcpListener::bind(&addr).await?;
-            println!("listening on {}", addr);
-            Self::print_router(router.as_ref())?;
-
-            loop {
-                let connection_result: tokio::io::Result<(TcpStream, SocketAddr)> = select! {
-                    _ = tokio::signal::ctrl_c() => break,
-                    r = listener.accept() => r,
-                };
-                let (stream, _addr) = match connection_result {
-                    Ok((stream, addr)) => (stream, addr),
-                    Err(e) => {
-                        eprintln!("connection error: {e}");
-                        continue;
-                    }
-                };
-
-                let http = http1::Builder::new();
-                let http = Arc::new(http);
-
-                let io = TokioIo::new(stream);
-                let routes = router.clone();
-                let context = context.clone();
-                tokio::spawn(async move {
-                    let service = service_fn(
-                        move |req| service(req, context.clone(), routes.clone())
-                    );
-                    let handler_result = http.serve_connection(io, service).await;
-
-                    match handler_result {
-                        Ok(()) => {}
-                        Err(e) => {
-                            eprintln!("handler error: {e}");
-                        }
-                    }
-                });
+    pub async fn run(self) -> anyhow::Result<()> {
+        let router = self.router.clone();
+        let addr = self.address;
+        let context = self.context;
+        let database = self.database;
+        let listener = TcpListener::bind(&addr).await?;
+        println!("listening on {}", addr);
+        Self::print_router(router.as_ref())?;
+
+        loop {
+            let connection_result: tokio::io::Result<(TcpStream, SocketAddr)> = select! {
+                _ = tokio::signal::ctrl_c() => break,
+                r = listener.accept() => r,
+            };
+            let (stream, _addr) = match connection_result {
+                Ok((stream, addr)) => (stream, addr),
+                Err(e) => {
+                    eprintln!("connection error: {e}");
+                    continue;
+                }
             };
 
-            eprintln!("shutting down...");
-            Result::<_, anyhow::Error>::Ok(())
-        })?;
+            let http = http1::Builder::new();
+            let http = Arc::new(http);
+
+            let io = TokioIo::new(stream);
+            let routes = router.clone();
+            let context = context.clone();
+            let database = database.clone();
+            tokio::spawn(async move {
+                let service = service_fn(
+                    move |req| service(req, context.clone(), database.clone(), routes.clone())
+                );
+                let handler_result = http.serve_connection(io, service).await;
+
+                match handler_result {
+                    Ok(()) => {}
+                    Err(e) => {
+                        eprintln!("handler error: {e}");
+                    }
+                }
+            });
+        };
 
-        rt.shutdown_background();
+        eprintln!("shutting down...");
         println!("shutdown gracefully");
         Ok(())
     }
@@ -168,6 +167,7 @@ impl Service {
 async fn service(
     req: Request<hyper::body::Incoming>,
     context: Arc<Context>,
+    database: Arc<db::Context>,
     routes: Arc<Router>,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     let path = req.uri().path();
@@ -194,9 +194,9 @@ async fn service(
         return Ok(not_found());
     };
 
-    let (sc, content) = match parse_file(context, path).await {
-        Ok(result) => {
-            (StatusCode::OK, result)
+    let (sc, content) = match run_file(context, database, path).await {
+        Ok(response) => {
+            (StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK), response.body)
         }
         Err(e) => {
             (StatusCode::INTERNAL_SERVER_ERROR, if cfg!(debug_assertions) {
@@ -211,14 +211,14 @@ async fn service(
     return Ok(res);
 }
 
-async fn parse_file(context: Arc<Context>, path: &Path) -> anyhow::Result<String> {
+async fn run_file(context: Arc<Context>, database: Arc<db::Context>, path: &Path) -> anyhow::Result<runtime::Response> {
     let contents = tokio::fs::read_to_string(path).await?;
     let file: SimplFile = contents.parse()?;
 
-    let mut runtime = Runtime::new(context.clone());
-    runtime.run(&file).await.map_err(|e| anyhow!("unable to run file: {e:?}"))?;
+    let mut runtime = Runtime::new(context.clone(), database);
+    runtime.run(&file).await.map_err(|e| anyhow!("unable to run file: {e}"))?;
 
-    Ok(format!("{:#?}", file))
+    Ok(runtime.into_response()?)
 }
 
 #[inline]
@@ -1,10 +1,11 @@
 use std::path::{Path, PathBuf};
+use hashbrown::HashSet;
 use thiserror::Error;
 use crate::debug_write;
 use crate::parser::ParseError;
-use crate::simpl::parser::{SimplFile};
+use crate::simpl::parser::{AttributeValue, Else, Expr, If, Section, SimplFile, UnaryOp};
 use crate::web::Context;
-use crate::web::context::RouteMap;
+use crate::web::context::{CodeMap, RouteMap};
 
 #[derive(Debug, Error)]
 pub enum ValidationError {
@@ -41,4 +42,113 @@ fn validate_route_handler(path: &Path) -> Result<(), ValidationError> {
     debug_write(&file, &*path.file_name().unwrap().to_string_lossy()).unwrap();
 
     Ok(())
-}
\ No newline at end of file
+}
+/// The qualified names of the queries route handlers and shared files call, `user.Login` for
+/// `db.user.Login(username, password)`.
+pub fn used_queries(context: &Context) -> Result<HashSet<String>, ValidationError> {
+    fn routes(map: &RouteMap, paths: &mut Vec<PathBuf>) {
+        paths.extend(map.handlers.values().cloned());
+        for inner in map.embedded.values() {
+            routes(inner, paths);
+        }
+    }
+    fn code(map: &CodeMap, paths: &mut Vec<PathBuf>) {
+        paths.extend(map.files.values().filter(|path| path.extension().is_some_and(|e| e == "simp")).cloned());
+        for child in map.children.values() {
+            code(child, paths);
+        }
+    }
+
+    let mut paths = Vec::new();
+    routes(&context.route_map, &mut paths);
+    code(&context.shared_code, &mut paths);
+
+    let mut names = HashSet::new();
+    for path in paths {
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| ValidationError::RouteMapError(RouteMapError::IoError(e)))?;
+        let file: SimplFile = contents.parse()
+            .map_err(|e| ValidationError::ParseError(path.clone(), e))?;
+        for expr in &file.statements {
+            query_calls(expr, &mut names);
+        }
+    }
+    Ok(names)
+}
+
+fn query_calls(expr: &Expr, names: &mut HashSet<String>) {
+    let exprs = |exprs: &[Expr], names: &mut HashSet<String>| {
+        for expr in exprs {
+            query_calls(expr, names);
+        }
+    };
+    match expr {
+        Expr::Unary(UnaryOp::Call(args), callee) => {
+            let mut path = Vec::new();
+            let mut inner = callee.as_ref();
+            while let Expr::Unary(UnaryOp::Access(name), next) = inner {
+                path.push(name.as_str());
+                inner = next;
+            }
+            if matches!(inner, Expr::Ident(root) if root == "db") && !path.is_empty() {
+                path.reverse();
+                names.insert(path.join("."));
+            }
+            query_calls(callee, names);
+            exprs(args, names);
+        }
+        Expr::Unary(UnaryOp::Index(index), inner) => {
+            query_calls(index, names);
+            query_calls(inner, names);
+        }
+        Expr::Unary(_, inner) | Expr::Group(inner) => query_calls(inner, names),
+        Expr::Binary(_, lhs, rhs) => {
+            query_calls(lhs, names);
+            query_calls(rhs, names);
+        }
+        Expr::If(if_) => if_calls(if_, names),
+        Expr::Block(block) | Expr::Transaction(block) => exprs(block, names),
+        Expr::Html(sections) => section_calls(sections, names),
+        Expr::Respond(fields) => {
+            for (_, expr) in fields {
+                query_calls(expr, names);
+            }
+        }
+        Expr::Ident(_) | Expr::Number(_) | Expr::String(_) | Expr::Decimal(_) | Expr::Bool(_) | Expr::Path(_) => {}
+    }
+}
+
+fn if_calls(if_: &If, names: &mut HashSet<String>) {
+    query_calls(&if_.condition, names);
+    for expr in &if_.then {
+        query_calls(expr, names);
+    }
+    match &if_.otherwise {
+        Some(Else::If(if_)) => if_calls(if_, names),
+        Some(Else::Block(block)) => {
+            for expr in block {
+                query_calls(expr, names);
+            }
+        }
+        None => {}
+    }
+}
+
+fn section_calls(sections: &[Section], names: &mut HashSet<String>) {
+    for section in sections {
+        match section {
+            Section::Element(element) => {
+                for (_, value) in &element.attributes {
+                    if let Some(AttributeValue::Expr(expr)) = value {
+                        query_calls(expr, names);
+                    }
+                }
+                if let Some(body) = &element.body {
+                    section_calls(body, names);
+                }
+            }
+            Section::Escaped(expr) | Section::Unescaped(expr) => query_calls(expr, names),
+            Section::Text(_) => {}
+        }
+    }
+}